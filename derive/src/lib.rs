@@ -20,11 +20,50 @@ use syn::{parse_macro_input, FnArg, GenericArgument, ItemFn, ItemForeignMod, Pat
 ///   Ok(s)
 /// }
 /// ```
+///
+/// ## Variable cache
+///
+/// `#[plugin_fn(var_cache)]` enables the `extism_pdk::var` write-back cache for the duration of
+/// the call. Variables are read from the host at most once, and modified variables are written
+/// back when the function returns.
+///
+/// ## Transactional variables
+///
+/// `#[plugin_fn(transactional_vars)]` runs the function in an `extism_pdk::var::transaction`.
+/// Variable writes are only applied when the function returns `Ok`, and are discarded when it
+/// returns an error.
+///
+/// ## Plugin state
+///
+/// Parameters with a `&mut` type are loaded using `extism_pdk::PluginState`, see
+/// `plugin_state`, and saved when the function returns `Ok` if they were modified. When the
+/// function returns an error, changes to the state are discarded. A function can take plugin
+/// state parameters in addition to its input.
+///
+/// ## Initialization
+///
+/// If the crate has a `plugin_init` function, it's called before the first `plugin_fn` body
+/// runs.
 #[proc_macro_attribute]
 pub fn plugin_fn(
-    _attr: proc_macro::TokenStream,
+    attr: proc_macro::TokenStream,
     item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
+    let mut var_cache = false;
+    let mut transactional_vars = false;
+    let attr_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("var_cache") {
+            var_cache = true;
+            Ok(())
+        } else if meta.path.is_ident("transactional_vars") {
+            transactional_vars = true;
+            Ok(())
+        } else {
+            Err(meta.error("unsupported plugin_fn attribute"))
+        }
+    });
+    parse_macro_input!(attr with attr_parser);
+
     let mut function = parse_macro_input!(item as ItemFn);
 
     if !matches!(function.vis, syn::Visibility::Public(..)) {
@@ -39,8 +78,6 @@ pub fn plugin_fn(
     let output = &mut function.sig.output;
     let block = &function.block;
 
-    let no_args = inputs.is_empty();
-
     if name == "main" {
         panic!(
             "extism_pdk::plugin_fn must not be applied to a `main` function. To fix, rename this to something other than `main`."
@@ -64,56 +101,313 @@ pub fn plugin_fn(
         }
     }
 
-    if no_args {
+    let mut has_input = false;
+    let mut call_args = vec![];
+    let mut load_state = quote!();
+    let mut save_state = quote!();
+    for (i, arg) in inputs.iter().enumerate() {
+        let FnArg::Typed(arg) = arg else {
+            panic!("extism_pdk::plugin_fn does not support self arguments");
+        };
+        match arg.ty.as_ref() {
+            syn::Type::Reference(r) if r.mutability.is_some() => {
+                let ty = &r.elem;
+                let state = Ident::new(&format!("state_{i}"), Span::call_site());
+                load_state.extend(quote! {
+                    let mut #state = extism_pdk::unwrap!(extism_pdk::state::StateCell::<#ty>::load());
+                });
+                call_args.push(quote!(#state.value()));
+                save_state.extend(quote! {
+                    if result.is_ok() {
+                        result = #state.save();
+                    }
+                });
+            }
+            _ => {
+                if has_input {
+                    panic!("extism_pdk::plugin_fn expects at most one input parameter, other parameters must be `&mut` plugin state");
+                }
+                has_input = true;
+                call_args.push(quote!(input));
+            }
+        }
+    }
+
+    let read_input = if has_input {
         quote! {
-            #[no_mangle]
-            pub #constness #unsafety extern "C" fn #name() -> i32 {
-                #constness #unsafety fn inner #generics() #output {
-                    #block
+            let input = match extism_pdk::input() {
+                core::result::Result::Ok(x) => x,
+                core::result::Result::Err(e) => {
+                    let err = format!("{:?}", e);
+                    let mut mem = extism_pdk::Memory::from_bytes(&err).unwrap();
+                    unsafe {
+                        extism_pdk::extism::error_set(mem.offset());
+                    }
+                    return extism_pdk::input_error_status(&e);
                 }
+            };
+        }
+    } else {
+        quote!()
+    };
 
-                let output = match inner() {
-                    core::result::Result::Ok(x) => x,
-                    core::result::Result::Err(rc) => {
-                        let err = format!("{:?}", rc.0);
-                        let mut mem = extism_pdk::Memory::from_bytes(&err).unwrap();
-                        unsafe {
-                            extism_pdk::extism::error_set(mem.offset());
-                        }
-                        return rc.1;
+    // State is only saved when the function returns `Ok`, it's saved first so it's part of the
+    // transaction and the cache. The transaction and the cache are always cleaned up, even
+    // when saving fails, and the first error is returned afterwards.
+    let mut begin = quote!();
+    let mut on_ok = quote!(
+        #[allow(unused_mut)]
+        let mut result: core::result::Result<(), extism_pdk::Error> = core::result::Result::Ok(());
+        #save_state
+    );
+    let mut on_err = quote!();
+    if transactional_vars {
+        // The transaction is committed before the cache is flushed
+        begin.extend(quote!(extism_pdk::var::begin_transaction();));
+        on_ok.extend(quote! {
+            if result.is_ok() {
+                result = extism_pdk::var::commit_transaction();
+            } else {
+                extism_pdk::var::rollback_transaction();
+            }
+        });
+        on_err.extend(quote!(extism_pdk::var::rollback_transaction();));
+    }
+    if var_cache {
+        begin.extend(quote!(extism_pdk::var::enable_cache();));
+        on_ok.extend(quote! {
+            let flushed = extism_pdk::var::disable_cache();
+            if result.is_ok() {
+                result = flushed;
+            }
+        });
+        on_err.extend(quote!(let _ = extism_pdk::var::disable_cache();));
+    }
+    on_ok.extend(quote!(extism_pdk::unwrap!(result);));
+
+    quote! {
+        #[no_mangle]
+        pub #constness #unsafety extern "C" fn #name() -> i32 {
+            #constness #unsafety fn inner #generics(#inputs) #output {
+                #block
+            }
+
+            {
+                #[allow(unused_imports)]
+                use extism_pdk::init::DefaultInit as _;
+                #[allow(unused_imports)]
+                use crate::*;
+                if let core::result::Result::Err(e) = (&extism_pdk::init::Hook).__extism_plugin_init() {
+                    return extism_pdk::init::report(&e);
+                }
+            }
+
+            #read_input
+            #load_state
+            #begin
+            let output = match inner(#(#call_args),*) {
+                core::result::Result::Ok(x) => x,
+                core::result::Result::Err(rc) => {
+                    #on_err
+                    let err = format!("{:?}", rc.0);
+                    let mut mem = extism_pdk::Memory::from_bytes(&err).unwrap();
+                    unsafe {
+                        extism_pdk::extism::error_set(mem.offset());
                     }
-                };
-                extism_pdk::unwrap!(extism_pdk::output(&output));
-                0
+                    return rc.1;
+                }
+            };
+            #on_ok
+            extism_pdk::unwrap!(extism_pdk::output(&output));
+            0
+        }
+    }
+    .into()
+}
+
+/// `plugin_state` implements `extism_pdk::PluginState` for a struct, so it can be passed to a
+/// `plugin_fn` as a `&mut` parameter. The state is loaded from a variable before the function
+/// is called, using `Default` when it isn't set, and saved when the function returns `Ok` if it
+/// was modified.
+///
+/// The struct must implement `Default` and the traits required by the encoding, for `Json` that
+/// is `serde::Serialize` and `serde::Deserialize`.
+///
+/// - `key = "..."` sets the variable name, the default is the name of the struct
+/// - `encoding = Type` sets the encoding, the default is `Json`
+///
+/// ## Example
+///
+/// ```rust
+/// use extism_pdk::{FnResult, plugin_fn, plugin_state};
+/// #[plugin_state(key = "counter")]
+/// #[derive(Default, serde::Serialize, serde::Deserialize)]
+/// pub struct Counter {
+///   count: u64,
+/// }
+///
+/// #[plugin_fn]
+/// pub fn count(state: &mut Counter) -> FnResult<u64> {
+///   state.count += 1;
+///   Ok(state.count)
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn plugin_state(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let mut key = None;
+    let mut encoding = None;
+    let attr_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("key") {
+            key = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            Ok(())
+        } else if meta.path.is_ident("encoding") {
+            encoding = Some(meta.value()?.parse::<syn::Path>()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported plugin_state attribute"))
+        }
+    });
+    parse_macro_input!(attr with attr_parser);
+
+    let input = parse_macro_input!(item as syn::DeriveInput);
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        panic!("extism_pdk::plugin_state does not support generic types");
+    }
+
+    let key = key.unwrap_or_else(|| name.to_string());
+    let encoding = encoding.unwrap_or_else(|| syn::parse_quote!(extism_pdk::Json));
+
+    quote! {
+        #input
+
+        impl extism_pdk::PluginState for #name {
+            const KEY: &'static str = #key;
+
+            fn decode(data: &[u8]) -> core::result::Result<Self, extism_pdk::Error> {
+                let x: #encoding<Self> = extism_pdk::FromBytesOwned::from_bytes_owned(data)?;
+                core::result::Result::Ok(x.0)
+            }
+
+            fn encode(&self) -> core::result::Result<std::vec::Vec<u8>, extism_pdk::Error> {
+                let bytes = extism_pdk::ToBytes::to_bytes(&#encoding(self))?;
+                core::result::Result::Ok(core::convert::AsRef::<[u8]>::as_ref(&bytes).to_vec())
             }
         }
-        .into()
-    } else {
+    }
+    .into()
+}
+
+/// `plugin_init` marks a function that runs once per plug-in instance, before the first call to
+/// any `plugin_fn`. It can be used for expensive setup like compiling regexes or parsing
+/// config. The function must be defined at the crate root, take no arguments and return
+/// `extism_pdk::FnResult<()>`.
+///
+/// The initializer is also exported using its own name, for hosts that call it eagerly. It
+/// only runs once, if it fails the error is reported by every later call. Defining it in any
+/// other module than the crate root is a compile error, since `plugin_fn` would not find it.
+///
+/// `#[plugin_init(export_initialize)]` also exports it as `_initialize`, following the WASI
+/// reactor convention. The export runs the wasm static constructors before the initializer,
+/// so it should only be used when the plug-in doesn't already export `_initialize`.
+///
+/// ## Example
+///
+/// ```rust
+/// use extism_pdk::{FnResult, plugin_init};
+/// #[plugin_init]
+/// pub fn init() -> FnResult<()> {
+///   extism_pdk::var::set("initialized", "true")?;
+///   Ok(())
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn plugin_init(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let mut export_initialize = false;
+    let attr_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("export_initialize") {
+            export_initialize = true;
+            Ok(())
+        } else {
+            Err(meta.error("unsupported plugin_init attribute"))
+        }
+    });
+    parse_macro_input!(attr with attr_parser);
+
+    let function = parse_macro_input!(item as ItemFn);
+
+    if !matches!(function.vis, syn::Visibility::Public(..)) {
+        panic!("extism_pdk::plugin_init expects a public function");
+    }
+    if !function.sig.inputs.is_empty() {
+        panic!("extism_pdk::plugin_init expects a function with no arguments");
+    }
+
+    let name = &function.sig.ident;
+    let output = &function.sig.output;
+    let block = &function.block;
+
+    if name == "_initialize" {
+        panic!("extism_pdk::plugin_init must not be applied to a function named `_initialize`");
+    }
+
+    let initialize = if export_initialize {
         quote! {
             #[no_mangle]
-            pub #constness #unsafety extern "C" fn #name() -> i32 {
-                #constness #unsafety fn inner #generics(#inputs) #output {
+            pub extern "C" fn _initialize() {
+                #[cfg(target_arch = "wasm32")]
+                {
+                    extern "C" {
+                        fn __wasm_call_ctors();
+                    }
+                    unsafe { __wasm_call_ctors() };
+                }
+                let _ = __ExtismPluginInit::__extism_plugin_init(&extism_pdk::init::Hook);
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        // `plugin_fn` only finds the initializer at the crate root
+        const _: () = {
+            #[allow(unused_imports)]
+            use crate::__ExtismPluginInit as _;
+        };
+
+        #[doc(hidden)]
+        pub trait __ExtismPluginInit {
+            fn __extism_plugin_init(&self) -> core::result::Result<(), (std::string::String, i32)>;
+        }
+
+        impl __ExtismPluginInit for extism_pdk::init::Hook {
+            fn __extism_plugin_init(&self) -> core::result::Result<(), (std::string::String, i32)> {
+                fn inner() #output {
                     #block
                 }
 
-                let input = extism_pdk::unwrap!(extism_pdk::input());
-                let output = match inner(input) {
-                    core::result::Result::Ok(x) => x,
-                    core::result::Result::Err(rc) => {
-                        let err = format!("{:?}", rc.0);
-                        let mut mem = extism_pdk::Memory::from_bytes(&err).unwrap();
-                        unsafe {
-                            extism_pdk::extism::error_set(mem.offset());
-                        }
-                        return rc.1;
-                    }
-                };
-                extism_pdk::unwrap!(extism_pdk::output(&output));
-                0
+                extism_pdk::init::run(inner)
+            }
+        }
+
+        #initialize
+
+        #[no_mangle]
+        pub extern "C" fn #name() -> i32 {
+            match __ExtismPluginInit::__extism_plugin_init(&extism_pdk::init::Hook) {
+                core::result::Result::Ok(()) => 0,
+                core::result::Result::Err(e) => extism_pdk::init::report(&e),
             }
         }
-        .into()
     }
+    .into()
 }
 
 /// `shared_fn` is used to define a function that will be exported by a plugin but is not directly
@@ -429,7 +723,10 @@ fn generate_host_fn_wrapper(
 
 /// Attempts to parse a `safe fn` from verbatim tokens
 /// Returns Some(wrapper) if successful, None if the tokens don't represent a safe fn
-fn parse_safe_fn_verbatim(namespace: &str, tokens: &proc_macro2::TokenStream) -> Option<proc_macro2::TokenStream> {
+fn parse_safe_fn_verbatim(
+    namespace: &str,
+    tokens: &proc_macro2::TokenStream,
+) -> Option<proc_macro2::TokenStream> {
     use syn::parse::{Parse, Parser};
 
     // Try to parse: [visibility] safe fn name(args) [-> ReturnType];
@@ -490,3 +787,287 @@ fn parse_safe_fn_verbatim(namespace: &str, tokens: &proc_macro2::TokenStream) ->
         Err(_) => None,
     }
 }
+
+/// `PluginConfig` is used to load a struct from plug-in config items
+///
+/// Each field is read from the config key with the same name, parsed using `FromStr`. The
+/// following field attributes are available:
+///
+/// - `#[config(key = "name")]` - use a different config key
+/// - `#[config(default = expr)]` - the value to use when the key is missing, string literals
+///   are parsed the same way as config values. For `Option<T>` fields `expr` is a `T`.
+///   `#[config(default)]` uses `Default::default()`
+/// - `#[config(required)]` - report an error when the key is missing, otherwise
+///   `Default::default()` is used for fields without a default
+/// - `#[config(with = path)]` - parse the value using a function `fn(&str) -> Result<T, E>`
+/// - `#[config(validate = path)]` - check the value using a function `fn(&T) -> Result<(), E>`
+/// - `#[config(nested)]` - load a field that also implements `PluginConfig` from keys prefixed
+///   with `field_name.`, or `#[config(nested, prefix = "...")]` to use a different prefix
+///
+/// `Option` fields are `None` when the key is missing. The struct itself can have a
+/// `#[config(validate = path)]` attribute, which is called with the loaded struct.
+///
+/// All missing and invalid keys are reported together in a single
+/// `extism_pdk::config::ConfigError`.
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// use extism_pdk::*;
+///
+/// #[derive(PluginConfig)]
+/// struct Settings {
+///     #[config(required)]
+///     api_url: String,
+///     #[config(default = "30s", with = config::parse_duration)]
+///     timeout: std::time::Duration,
+///     region: Option<String>,
+/// }
+///
+/// #[plugin_fn]
+/// pub fn run() -> FnResult<String> {
+///     let settings = Settings::get()?;
+///     Ok(settings.api_url.clone())
+/// }
+/// ```
+#[proc_macro_derive(PluginConfig, attributes(config))]
+pub fn plugin_config(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(item as syn::DeriveInput);
+    let name = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        panic!("extism_pdk::PluginConfig cannot be derived for generic types");
+    }
+
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => panic!("extism_pdk::PluginConfig can only be derived for structs with named fields"),
+    };
+
+    let mut struct_validate = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("config")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("validate") {
+                struct_validate = Some(meta.value()?.parse::<syn::Path>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported config attribute"))
+            }
+        })
+        .unwrap_or_else(|e| panic!("extism_pdk::PluginConfig: {e}"));
+    }
+
+    let mut loads = vec![];
+    let mut names = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let attrs = ConfigFieldAttrs::parse(field);
+        let ty = &field.ty;
+        names.push(ident);
+
+        if attrs.nested {
+            let prefix = attrs.prefix.unwrap_or_else(|| format!("{ident}."));
+            loads.push(quote! {
+                let #ident: core::option::Option<#ty> =
+                    <#ty as extism_pdk::config::PluginConfig>::load_prefixed(
+                        &format!("{}{}", prefix, #prefix),
+                        errors,
+                    );
+            });
+            continue;
+        }
+
+        let key = attrs.key.unwrap_or_else(|| ident.to_string());
+        let inner = option_inner(ty);
+        let value_ty = inner.unwrap_or(ty);
+        let wrap = if inner.is_some() {
+            quote!(core::option::Option::Some(x))
+        } else {
+            quote!(x)
+        };
+        let parser = match &attrs.with {
+            Some(with) => quote!(|s: &str| #with(s)),
+            None => quote!(<#value_ty as core::str::FromStr>::from_str),
+        };
+
+        let missing = match attrs.default {
+            Some(ConfigDefault::Expr(syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(s),
+                ..
+            }))) => quote! {
+                match (#parser)(#s) {
+                    core::result::Result::Ok(x) => core::option::Option::Some(#wrap),
+                    core::result::Result::Err(e) => {
+                        errors.push(format!("Invalid default for config key {:?}: {}", key, e));
+                        core::option::Option::None
+                    }
+                }
+            },
+            Some(ConfigDefault::Expr(expr)) => quote! {
+                {
+                    let x: #value_ty = #expr;
+                    core::option::Option::Some(#wrap)
+                }
+            },
+            Some(ConfigDefault::Default) => {
+                quote!(core::option::Option::Some(core::default::Default::default()))
+            }
+            None if attrs.required => quote! {
+                {
+                    errors.push(format!("Missing required config key {:?}", key));
+                    core::option::Option::None
+                }
+            },
+            None => quote!(core::option::Option::Some(core::default::Default::default())),
+        };
+
+        let validate = match &attrs.validate {
+            Some(validate) => quote! {
+                if let core::option::Option::Some(x) = &value {
+                    if let core::result::Result::Err(e) = #validate(x) {
+                        errors.push(format!("Invalid value for config key {:?}: {}", key, e));
+                    }
+                }
+            },
+            None => quote!(),
+        };
+
+        loads.push(quote! {
+            let #ident: core::option::Option<#ty> = {
+                let key = format!("{}{}", prefix, #key);
+                let value = match extism_pdk::config::parse_value::<#value_ty, _>(&key, #parser) {
+                    core::result::Result::Ok(core::option::Option::Some(x)) => {
+                        core::option::Option::Some(#wrap)
+                    }
+                    core::result::Result::Ok(core::option::Option::None) => #missing,
+                    core::result::Result::Err(e) => {
+                        errors.push(e.to_string());
+                        core::option::Option::None
+                    }
+                };
+                #validate
+                value
+            };
+        });
+    }
+
+    let struct_validate = match struct_validate {
+        Some(validate) => quote! {
+            if let core::result::Result::Err(e) = #validate(&value) {
+                errors.push(e.to_string());
+                return core::option::Option::None;
+            }
+        },
+        None => quote!(),
+    };
+
+    quote! {
+        impl extism_pdk::config::PluginConfig for #name {
+            fn load_prefixed(
+                prefix: &str,
+                errors: &mut Vec<String>,
+            ) -> core::option::Option<Self> {
+                let start = errors.len();
+                #(#loads)*
+
+                if errors.len() != start {
+                    return core::option::Option::None;
+                }
+
+                let value = Self {
+                    #(#names: #names.unwrap(),)*
+                };
+                #struct_validate
+                core::option::Option::Some(value)
+            }
+
+            fn get() -> core::result::Result<&'static Self, extism_pdk::config::ConfigError> {
+                static CONFIG: std::sync::OnceLock<#name> = std::sync::OnceLock::new();
+                if let core::option::Option::Some(x) = CONFIG.get() {
+                    return core::result::Result::Ok(x);
+                }
+                let x = <Self as extism_pdk::config::PluginConfig>::load()?;
+                core::result::Result::Ok(CONFIG.get_or_init(|| x))
+            }
+        }
+    }
+    .into()
+}
+
+enum ConfigDefault {
+    Default,
+    Expr(syn::Expr),
+}
+
+#[derive(Default)]
+struct ConfigFieldAttrs {
+    key: Option<String>,
+    default: Option<ConfigDefault>,
+    required: bool,
+    with: Option<syn::Path>,
+    validate: Option<syn::Path>,
+    nested: bool,
+    prefix: Option<String>,
+}
+
+impl ConfigFieldAttrs {
+    fn parse(field: &syn::Field) -> Self {
+        let mut attrs = ConfigFieldAttrs::default();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("config")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("key") {
+                    attrs.key = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    attrs.default = if meta.input.peek(syn::Token![=]) {
+                        Some(ConfigDefault::Expr(meta.value()?.parse()?))
+                    } else {
+                        Some(ConfigDefault::Default)
+                    };
+                } else if meta.path.is_ident("required") {
+                    attrs.required = true;
+                } else if meta.path.is_ident("with") {
+                    attrs.with = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("validate") {
+                    attrs.validate = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("nested") {
+                    attrs.nested = true;
+                } else if meta.path.is_ident("prefix") {
+                    attrs.prefix = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+                } else {
+                    return Err(meta.error("unsupported config attribute"));
+                }
+                Ok(())
+            })
+            .unwrap_or_else(|e| panic!("extism_pdk::PluginConfig: {e}"));
+        }
+
+        if attrs.required && attrs.default.is_some() {
+            panic!(
+                "extism_pdk::PluginConfig: a field cannot be both `required` and have a `default`"
+            );
+        }
+
+        attrs
+    }
+}
+
+/// Returns the inner type if `ty` is an `Option`
+fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(p) = ty else {
+        return None;
+    };
+    let segment = p.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first() {
+        Some(GenericArgument::Type(t)) if args.args.len() == 1 => Some(t),
+        _ => None,
+    }
+}
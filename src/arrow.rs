@@ -0,0 +1,79 @@
+use std::io::BufReader;
+
+use arrow_ipc::{reader::StreamReader, writer::StreamWriter};
+
+use crate::*;
+
+/// Re-export of `arrow_array`
+pub use arrow_array;
+
+pub use arrow_array::RecordBatch;
+
+/// Arrow IPC stream encoding
+///
+/// When decoding, every record batch in the stream is read into memory. Use [input_stream]
+/// to process plugin input one batch at a time instead. When encoding, the schema of the
+/// first batch is used for the whole stream, so at least one batch is required.
+#[derive(Debug)]
+pub struct ArrowIpc(pub Vec<RecordBatch>);
+
+impl ArrowIpc {
+    pub fn into_inner(self) -> Vec<RecordBatch> {
+        self.0
+    }
+}
+
+impl From<Vec<RecordBatch>> for ArrowIpc {
+    fn from(data: Vec<RecordBatch>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<RecordBatch> for ArrowIpc {
+    fn from(data: RecordBatch) -> Self {
+        Self(vec![data])
+    }
+}
+
+impl FromBytesOwned for ArrowIpc {
+    fn from_bytes_owned(data: &[u8]) -> Result<Self, Error> {
+        let reader = StreamReader::try_new(data, None)?;
+        let batches = reader.collect::<Result<Vec<_>, _>>()?;
+        Ok(ArrowIpc(batches))
+    }
+}
+
+impl ToBytes<'_> for ArrowIpc {
+    type Bytes = Vec<u8>;
+
+    fn to_bytes(&self) -> Result<Self::Bytes, Error> {
+        let schema = match self.0.first() {
+            Some(batch) => batch.schema(),
+            None => anyhow::bail!("Unable to encode Arrow IPC stream without any record batches"),
+        };
+        let mut writer = StreamWriter::try_new(Vec::new(), &schema)?;
+        for batch in &self.0 {
+            writer.write(batch)?;
+        }
+        Ok(writer.into_inner()?)
+    }
+}
+
+/// Read the plugin input as an Arrow IPC stream, record batches are decoded
+/// as they are read from the host
+///
+/// # Examples
+///
+/// ```
+/// #[plugin_fn]
+/// pub fn row_count() -> FnResult<u64> {
+///     let mut rows = 0;
+///     for batch in arrow::input_stream()? {
+///         rows += batch?.num_rows() as u64;
+///     }
+///     Ok(rows)
+/// }
+/// ```
+pub fn input_stream() -> Result<StreamReader<BufReader<InputReader>>, Error> {
+    Ok(StreamReader::try_new_buffered(input_reader(), None)?)
+}
@@ -1,3 +1,5 @@
+use anyhow::Context;
+
 use crate::*;
 
 pub fn get_memory(key: impl AsRef<str>) -> Result<Option<Memory>, Error> {
@@ -29,10 +31,439 @@ pub fn get_memory(key: impl AsRef<str>) -> Result<Option<Memory>, Error> {
 /// # Examples
 ///
 /// ```
-/// // let's assume we have a config object: { my_config: 42u32 }
-/// // which is a u32. We can default to 0 first time we fetch it if it's not present
-/// let my_config = config::get("my_config")?.unwrap_or(0u32);
+/// // let's assume we have a config object: { my_config: "hello" }
+/// // We can default to an empty string if it's not present
+/// let my_config = config::get("my_config")?.unwrap_or_default();
 /// ```
 pub fn get(key: impl AsRef<str>) -> Result<Option<String>, Error> {
-    Ok(get_memory(key)?.map(|x| x.to_string().expect("Config value is not a valid string")))
+    let key = key.as_ref();
+    match get_bytes(key)? {
+        Some(x) => Ok(Some(String::from_utf8(x).with_context(|| {
+            format!("Config value {key:?} is not a valid string")
+        })?)),
+        None => Ok(None),
+    }
+}
+
+/// Gets a config item passed in from the host as raw bytes
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+pub fn get_bytes(key: impl AsRef<str>) -> Result<Option<Vec<u8>>, Error> {
+    match get_memory(key)? {
+        Some(mem) => {
+            let x = mem.to_vec();
+            mem.free();
+            Ok(Some(x))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Gets a config item that holds sensitive data, such as an API key. The value is wrapped
+/// in a [Secret] so it can't be accidentally logged and is zeroed when dropped, the memory
+/// it was read from is zeroed and freed immediately.
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+///
+/// # Examples
+///
+/// ```
+/// let api_key = config::get_secret("api_key")?.expect("api_key is set");
+/// let req = HttpRequest::new("https://example.com")
+///     .with_header("Authorization", format!("Bearer {}", api_key.expose()));
+/// ```
+pub fn get_secret(key: impl AsRef<str>) -> Result<Option<Secret<String>>, Error> {
+    let key = key.as_ref();
+    let Some(mem) = get_memory(key)? else {
+        return Ok(None);
+    };
+    let data = mem.to_vec();
+    mem.zero_and_free();
+
+    match String::from_utf8(data) {
+        Ok(s) => Ok(Some(Secret::new(s))),
+        Err(e) => {
+            zeroize::Zeroize::zeroize(&mut e.into_bytes());
+            anyhow::bail!("Config value {key:?} is not a valid string")
+        }
+    }
+}
+
+/// Gets an encrypted config item passed in from the host and decrypts it
+///
+/// Encrypted values use the `enc:v1:<base64>` format created by [encryption::encrypt], with
+/// the config key name used as associated data, so an encrypted value can't be moved to a
+/// different key. Returns an error if the value isn't encrypted or has been tampered with.
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+/// * `encryption_key` - The key used to decrypt the value
+///
+/// # Examples
+///
+/// ```
+/// let encryption_key = EncryptionKey::from_config("encryption_key")?;
+/// let password = config::get_decrypted("db_password", &encryption_key)?;
+/// ```
+#[cfg(feature = "encryption")]
+pub fn get_decrypted(
+    key: impl AsRef<str>,
+    encryption_key: &EncryptionKey,
+) -> Result<Option<Secret<String>>, Error> {
+    let key = key.as_ref();
+    let Some(value) = get_secret(key)? else {
+        return Ok(None);
+    };
+    let plaintext = encryption::decrypt(encryption_key, key.as_bytes(), value.expose())
+        .with_context(|| format!("Unable to decrypt config value {key:?}"))?;
+
+    match String::from_utf8(plaintext.expose().clone()) {
+        Ok(s) => Ok(Some(Secret::new(s))),
+        Err(e) => {
+            zeroize::Zeroize::zeroize(&mut e.into_bytes());
+            anyhow::bail!("Config value {key:?} is not a valid string")
+        }
+    }
+}
+
+/// Gets a config item passed in from the host and parses it using the `FromStr`
+/// implementation of `T`
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+///
+/// # Examples
+///
+/// ```
+/// // let's assume we have a config object: { my_config: "42" }
+/// // We can default to 0 if it's not present
+/// let my_config = config::get_as::<u32>("my_config")?.unwrap_or(0);
+/// ```
+pub fn get_as<T: std::str::FromStr>(key: impl AsRef<str>) -> Result<Option<T>, Error>
+where
+    T::Err: std::fmt::Display,
+{
+    parse_value(key.as_ref(), T::from_str)
+}
+
+/// Gets a config item passed in from the host and decodes it as JSON
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+pub fn get_json<T: serde::de::DeserializeOwned>(key: impl AsRef<str>) -> Result<Option<T>, Error> {
+    Ok(get_decoded::<Json<T>>(key)?.map(|Json(x)| x))
+}
+
+/// Gets a boolean config item, `true`, `yes`, `on` and `1` are accepted as `true` and
+/// `false`, `no`, `off` and `0` are accepted as `false`, ignoring case
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+pub fn get_bool(key: impl AsRef<str>) -> Result<Option<bool>, Error> {
+    parse_value(key.as_ref(), parse_bool)
+}
+
+/// Gets a duration config item, a duration is a sequence of numbers followed by one of the
+/// units `ns`, `us`, `ms`, `s`, `m`, `h` or `d`, i.e. `1h30m` or `250ms`. A number without a
+/// unit is treated as seconds.
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+pub fn get_duration(key: impl AsRef<str>) -> Result<Option<std::time::Duration>, Error> {
+    parse_value(key.as_ref(), parse_duration)
+}
+
+/// Gets a byte size config item, a byte size is a number optionally followed by a unit:
+/// `B`, `KB`, `MB`, `GB`, `TB` for powers of 1000 or `KiB`, `MiB`, `GiB`, `TiB` for powers of
+/// 1024, ignoring case, i.e. `64MiB`
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+pub fn get_byte_size(key: impl AsRef<str>) -> Result<Option<u64>, Error> {
+    parse_value(key.as_ref(), parse_byte_size)
+}
+
+/// Gets a config item passed in from the host and decodes it using the `FromBytesOwned`
+/// implementation of `T`. This can be used to load a structured document stored in a single
+/// config item.
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+///
+/// # Examples
+///
+/// ```
+/// // let's assume we have a config object: { rules: "<yaml document>" }
+/// let rules = config::get_decoded::<Yaml<Rules>>("rules")?;
+/// ```
+pub fn get_decoded<T: FromBytesOwned>(key: impl AsRef<str>) -> Result<Option<T>, Error> {
+    let key = key.as_ref();
+    match get_memory(key)? {
+        Some(mem) => {
+            let x = mem.to::<T>();
+            mem.free();
+            Ok(Some(x.with_context(|| {
+                format!("Unable to decode config value {key:?}")
+            })?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// A struct that can be loaded from config items, this should be implemented using
+/// `#[derive(PluginConfig)]`
+///
+/// # Examples
+///
+/// ```
+/// #[derive(PluginConfig)]
+/// #[config(validate = check_limits)]
+/// struct Settings {
+///     #[config(required)]
+///     api_url: String,
+///     #[config(key = "timeout_secs", default = "30s", with = config::parse_duration)]
+///     timeout: std::time::Duration,
+///     #[config(default = 3, validate = nonzero)]
+///     retries: u32,
+///     // Loaded from `cache.size`, `cache.ttl`, ...
+///     #[config(nested)]
+///     cache: CacheSettings,
+///     // Optional fields are `None` when the key is missing
+///     region: Option<String>,
+/// }
+///
+/// let settings = Settings::get()?;
+/// ```
+pub trait PluginConfig: Sized + 'static {
+    /// Load the config with `prefix` prepended to every key, problems are added to `errors`
+    /// and `None` is returned if there were any
+    fn load_prefixed(prefix: &str, errors: &mut Vec<String>) -> Option<Self>;
+
+    /// Get the config, it is loaded the first time this is called and then cached since config
+    /// items don't change for the lifetime of the plug-in
+    fn get() -> Result<&'static Self, ConfigError>;
+
+    /// Load the config without using the cache
+    fn load() -> Result<Self, ConfigError> {
+        let mut errors = vec![];
+        match Self::load_prefixed("", &mut errors) {
+            Some(x) if errors.is_empty() => Ok(x),
+            _ => Err(ConfigError { errors }),
+        }
+    }
+}
+
+/// The error returned when a [PluginConfig] can't be loaded, it lists every missing or
+/// invalid config item
+#[derive(Debug)]
+pub struct ConfigError {
+    pub errors: Vec<String>,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid plug-in config: {}", self.errors.join("; "))
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Gets a config item and parses it using `f`, the error message includes the key if
+/// parsing fails
+pub fn parse_value<T, E: std::fmt::Display>(
+    key: &str,
+    f: impl FnOnce(&str) -> Result<T, E>,
+) -> Result<Option<T>, Error> {
+    match get(key)? {
+        Some(s) => match f(s.trim()) {
+            Ok(x) => Ok(Some(x)),
+            Err(e) => anyhow::bail!("Invalid value for config key {key:?}: {e}"),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Parse a boolean, see [get_bool]
+pub fn parse_bool(s: &str) -> Result<bool, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(format!("expected a boolean, got {s:?}")),
+    }
+}
+
+fn split_number(s: &str) -> Result<(&str, &str, &str), String> {
+    let end = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    if end == 0 {
+        return Err(format!("expected a number, got {s:?}"));
+    }
+    let n = &s[..end];
+    if n.parse::<f64>().is_err() {
+        return Err(format!("expected a number, got {n:?}"));
+    }
+    let rest = &s[end..];
+    let unit_end = rest
+        .find(|c: char| c.is_ascii_digit() || c == '.')
+        .unwrap_or(rest.len());
+    Ok((n, rest[..unit_end].trim(), &rest[unit_end..]))
+}
+
+/// Parse a duration, see [get_duration]
+pub fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+    if s.is_empty() {
+        return Err("expected a duration, got an empty string".to_string());
+    }
+    let too_large = || format!("duration {s:?} is too large");
+
+    // Whole units are added exactly as nanoseconds, floats are only used for fractional parts
+    let mut total: u128 = 0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let (n, unit, next) = split_number(rest)?;
+        let scale: u64 = match unit {
+            "ns" => 1,
+            "us" => 1_000,
+            "ms" => 1_000_000,
+            "" | "s" => 1_000_000_000,
+            "m" => 60 * 1_000_000_000,
+            "h" => 3600 * 1_000_000_000,
+            "d" => 86400 * 1_000_000_000,
+            _ => return Err(format!("unknown duration unit {unit:?}")),
+        };
+        let (whole, fraction) = n.split_once('.').unwrap_or((n, ""));
+        let whole = match whole {
+            "" => 0,
+            _ => whole.parse::<u64>().map_err(|_| too_large())?,
+        };
+        let fraction = match fraction {
+            "" => 0,
+            _ => (format!("0.{fraction}").parse::<f64>().unwrap() * scale as f64).round() as u128,
+        };
+        total = total
+            .checked_add(whole as u128 * scale as u128 + fraction)
+            .ok_or_else(too_large)?;
+        rest = next;
+    }
+
+    let secs = u64::try_from(total / 1_000_000_000).map_err(|_| too_large())?;
+    let nanos = (total % 1_000_000_000) as u32;
+    Ok(std::time::Duration::new(secs, nanos))
+}
+
+/// Parse a byte size, see [get_byte_size]
+pub fn parse_byte_size(s: &str) -> Result<u64, String> {
+    let (n, unit, rest) = split_number(s)?;
+    if !rest.is_empty() {
+        return Err(format!("expected a single byte size, got {s:?}"));
+    }
+    let scale: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000u64.pow(2),
+        "gb" => 1000u64.pow(3),
+        "tb" => 1000u64.pow(4),
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return Err(format!("unknown byte size unit {unit:?}")),
+    };
+    let too_large = || format!("byte size {s:?} is too large");
+
+    // Integers are scaled exactly, floats are only used for fractional sizes like `1.5GiB`
+    if !n.contains('.') {
+        return n
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(scale))
+            .ok_or_else(too_large);
+    }
+    let size = n.parse::<f64>().unwrap() * scale as f64;
+    // `u64::MAX as f64` rounds up to 2^64, which doesn't fit in a `u64`
+    if size >= u64::MAX as f64 {
+        return Err(too_large());
+    }
+    Ok(size as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_size() {
+        assert_eq!(parse_byte_size("0"), Ok(0));
+        assert_eq!(parse_byte_size("64MiB"), Ok(64 << 20));
+        assert_eq!(parse_byte_size("1.5 KiB"), Ok(1536));
+        assert_eq!(parse_byte_size("18446744073709551615"), Ok(u64::MAX));
+        assert_eq!(parse_byte_size("9007199254740993"), Ok(9007199254740993));
+        assert_eq!(parse_byte_size("16777215TiB"), Ok(16777215 << 40));
+        assert!(parse_byte_size("18446744073709551616").is_err());
+        assert!(parse_byte_size("16777216.0TiB").is_err());
+        assert!(parse_byte_size("16777216TiB").is_err());
+        assert!(parse_byte_size("18446744073709551616.0").is_err());
+        assert!(parse_byte_size("1.2.3").is_err());
+        assert!(parse_byte_size("1PB").is_err());
+    }
+
+    #[test]
+    fn duration() {
+        use std::time::Duration;
+
+        assert_eq!(parse_duration("0"), Ok(Duration::ZERO));
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("1h 30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1h30m15s"), Ok(Duration::from_secs(5415)));
+        assert_eq!(parse_duration("2 m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1.s"), Ok(Duration::from_secs(1)));
+        assert_eq!(parse_duration(".5m"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("0.1ms"), Ok(Duration::from_micros(100)));
+        assert_eq!(
+            parse_duration("9007199254740993ns"),
+            Ok(Duration::from_nanos(9007199254740993))
+        );
+        assert_eq!(
+            parse_duration("18446744073709551615ns"),
+            Ok(Duration::from_nanos(u64::MAX))
+        );
+        assert_eq!(
+            parse_duration("213503982334601d"),
+            Ok(Duration::from_secs(213503982334601 * 86400))
+        );
+        assert!(parse_duration("213503982334602d").is_err());
+        assert!(parse_duration("18446744073709551616s").is_err());
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration(".").is_err());
+        assert!(parse_duration("1.2.3s").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("5x").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn boolean() {
+        for s in ["true", "YES", "On", "1"] {
+            assert_eq!(parse_bool(s), Ok(true), "input: {s}");
+        }
+        for s in ["false", "no", "OFF", "0"] {
+            assert_eq!(parse_bool(s), Ok(false), "input: {s}");
+        }
+        for s in ["", "2", "y", "truee"] {
+            assert!(parse_bool(s).is_err(), "input: {s}");
+        }
+    }
 }
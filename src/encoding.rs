@@ -0,0 +1,688 @@
+use base64::Engine;
+
+macro_rules! base64_encoding {
+    ($name:ident, $engine:expr, $doc:literal) => {
+        #[doc = $doc]
+        ///
+        /// When encoding, the inner value is converted to bytes using its `ToBytes`
+        /// implementation and then base64 encoded. When decoding, the input is base64
+        /// decoded and passed to the inner `FromBytesOwned` implementation, this allows
+        /// other encodings to be wrapped, i.e. `Base64<Msgpack<T>>`.
+        #[derive(Debug)]
+        pub struct $name<T = Vec<u8>>(pub T);
+
+        impl<T> $name<T> {
+            pub fn into_inner(self) -> T {
+                self.0
+            }
+        }
+
+        impl<T> From<T> for $name<T> {
+            fn from(data: T) -> Self {
+                Self(data)
+            }
+        }
+
+        impl<'a, T: crate::ToBytes<'a>> crate::ToBytes<'a> for $name<T> {
+            type Bytes = String;
+
+            fn to_bytes(&self) -> Result<Self::Bytes, crate::Error> {
+                let data = self.0.to_bytes()?;
+                Ok($engine.encode(data.as_ref()))
+            }
+        }
+
+        impl<T: crate::FromBytesOwned> crate::FromBytesOwned for $name<T> {
+            fn from_bytes_owned(data: &[u8]) -> Result<Self, crate::Error> {
+                let data = $engine.decode(data)?;
+                Ok($name(T::from_bytes_owned(&data)?))
+            }
+        }
+    };
+}
+
+base64_encoding!(
+    Base64,
+    base64::engine::general_purpose::STANDARD,
+    "Base64 encoding using the standard alphabet with padding"
+);
+
+base64_encoding!(
+    Base64NoPad,
+    base64::engine::general_purpose::STANDARD_NO_PAD,
+    "Base64 encoding using the standard alphabet without padding"
+);
+
+base64_encoding!(
+    Base64Url,
+    base64::engine::general_purpose::URL_SAFE,
+    "Base64 encoding using the URL-safe alphabet with padding"
+);
+
+base64_encoding!(
+    Base64UrlNoPad,
+    base64::engine::general_purpose::URL_SAFE_NO_PAD,
+    "Base64 encoding using the URL-safe alphabet without padding"
+);
+
+/// Hex encoding
+///
+/// Values are encoded as lowercase hex, both lowercase and uppercase input can be decoded.
+/// Like [Base64], other encodings can be wrapped, i.e. `Hex<Json<T>>`.
+#[derive(Debug)]
+pub struct Hex<T = Vec<u8>>(pub T);
+
+impl<T> Hex<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Hex<T> {
+    fn from(data: T) -> Self {
+        Self(data)
+    }
+}
+
+impl<'a, T: crate::ToBytes<'a>> crate::ToBytes<'a> for Hex<T> {
+    type Bytes = String;
+
+    fn to_bytes(&self) -> Result<Self::Bytes, crate::Error> {
+        let data = self.0.to_bytes()?;
+        Ok(hex::encode(data.as_ref()))
+    }
+}
+
+impl<T: crate::FromBytesOwned> crate::FromBytesOwned for Hex<T> {
+    fn from_bytes_owned(data: &[u8]) -> Result<Self, crate::Error> {
+        let data = hex::decode(data)?;
+        Ok(Hex(T::from_bytes_owned(&data)?))
+    }
+}
+
+#[cfg(feature = "cbor")]
+fn cbor_to_vec<T: serde::Serialize>(x: &T) -> Result<Vec<u8>, crate::Error> {
+    let mut buf = Vec::new();
+    ciborium::into_writer(x, &mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(feature = "cbor")]
+fn cbor_from_slice<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T, crate::Error> {
+    Ok(ciborium::from_reader(data)?)
+}
+
+#[cfg(feature = "cbor")]
+extism_convert::encoding!(pub Cbor, cbor_to_vec, cbor_from_slice);
+
+#[cfg(feature = "postcard")]
+fn postcard_to_vec<T: serde::Serialize>(x: &T) -> Result<Vec<u8>, crate::Error> {
+    Ok(postcard::to_allocvec(x)?)
+}
+
+#[cfg(feature = "postcard")]
+fn postcard_from_slice<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T, crate::Error> {
+    Ok(postcard::from_bytes(data)?)
+}
+
+#[cfg(feature = "postcard")]
+extism_convert::encoding!(pub Postcard, postcard_to_vec, postcard_from_slice);
+
+#[cfg(feature = "bincode")]
+fn bincode_to_vec<T: serde::Serialize>(x: &T) -> Result<Vec<u8>, crate::Error> {
+    Ok(bincode::serde::encode_to_vec(
+        x,
+        bincode::config::standard(),
+    )?)
+}
+
+#[cfg(feature = "bincode")]
+fn bincode_from_slice<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T, crate::Error> {
+    let (x, _) = bincode::serde::decode_from_slice(data, bincode::config::standard())?;
+    Ok(x)
+}
+
+#[cfg(feature = "bincode")]
+extism_convert::encoding!(pub Bincode, bincode_to_vec, bincode_from_slice);
+
+#[cfg(feature = "yaml")]
+fn yaml_to_vec<T: serde::Serialize>(x: &T) -> Result<Vec<u8>, crate::Error> {
+    Ok(serde_yaml::to_string(x)?.into_bytes())
+}
+
+#[cfg(feature = "yaml")]
+fn yaml_from_slice<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T, crate::Error> {
+    Ok(serde_yaml::from_slice(data)?)
+}
+
+#[cfg(feature = "yaml")]
+extism_convert::encoding!(pub Yaml, yaml_to_vec, yaml_from_slice);
+
+#[cfg(feature = "toml")]
+fn toml_to_vec<T: serde::Serialize>(x: &T) -> Result<Vec<u8>, crate::Error> {
+    Ok(toml::to_string(x)?.into_bytes())
+}
+
+#[cfg(feature = "toml")]
+fn toml_from_slice<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T, crate::Error> {
+    Ok(toml::from_slice(data)?)
+}
+
+#[cfg(feature = "toml")]
+extism_convert::encoding!(pub Toml, toml_to_vec, toml_from_slice);
+
+/// Gzip compression
+///
+/// `Gzip` wraps another encoding and compresses its output, i.e. `Gzip<Json<T>>`. When
+/// decoding, the input is decompressed before being passed to the inner encoding.
+#[cfg(feature = "gzip")]
+#[derive(Debug)]
+pub struct Gzip<T>(pub T);
+
+#[cfg(feature = "gzip")]
+impl<T> Gzip<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl<T> From<T> for Gzip<T> {
+    fn from(data: T) -> Self {
+        Self(data)
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl<'a, T: crate::ToBytes<'a>> crate::ToBytes<'a> for Gzip<T> {
+    type Bytes = Vec<u8>;
+
+    fn to_bytes(&self) -> Result<Self::Bytes, crate::Error> {
+        use std::io::Write;
+
+        let data = self.0.to_bytes()?;
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        enc.write_all(data.as_ref())?;
+        Ok(enc.finish()?)
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl<T: crate::FromBytesOwned> crate::FromBytesOwned for Gzip<T> {
+    fn from_bytes_owned(data: &[u8]) -> Result<Self, crate::Error> {
+        use std::io::Read;
+
+        let mut buf = Vec::new();
+        flate2::read::GzDecoder::new(data).read_to_end(&mut buf)?;
+        Ok(Gzip(T::from_bytes_owned(&buf)?))
+    }
+}
+
+/// Zstandard compression
+///
+/// `Zstd` wraps another encoding and compresses its output, i.e. `Zstd<Msgpack<T>>`. When
+/// decoding, the input is decompressed before being passed to the inner encoding.
+#[cfg(feature = "zstd")]
+#[derive(Debug)]
+pub struct Zstd<T>(pub T);
+
+#[cfg(feature = "zstd")]
+impl<T> Zstd<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<T> From<T> for Zstd<T> {
+    fn from(data: T) -> Self {
+        Self(data)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<'a, T: crate::ToBytes<'a>> crate::ToBytes<'a> for Zstd<T> {
+    type Bytes = Vec<u8>;
+
+    fn to_bytes(&self) -> Result<Self::Bytes, crate::Error> {
+        let data = self.0.to_bytes()?;
+        Ok(ruzstd::encoding::compress_to_vec(
+            data.as_ref(),
+            ruzstd::encoding::CompressionLevel::Fastest,
+        ))
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<T: crate::FromBytesOwned> crate::FromBytesOwned for Zstd<T> {
+    fn from_bytes_owned(mut data: &[u8]) -> Result<Self, crate::Error> {
+        use std::io::Read;
+
+        let mut buf = Vec::new();
+        ruzstd::decoding::StreamingDecoder::new(&mut data)?.read_to_end(&mut buf)?;
+        Ok(Zstd(T::from_bytes_owned(&buf)?))
+    }
+}
+
+/// Returns `false` if `data` uses anything `simd-json` decodes differently from `serde_json`:
+/// escaped surrogates, nesting past the `serde_json` recursion limit, negative zero and
+/// floats that `serde_json` is unable to round exactly
+#[cfg(feature = "simd-json")]
+fn simd_json_compatible(data: &[u8]) -> bool {
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            b'"' => {
+                i += 1;
+                while i < data.len() && data[i] != b'"' {
+                    if data[i] == b'\\' {
+                        if data[i + 1..].starts_with(b"u")
+                            && matches!(data.get(i + 2), Some(b'd' | b'D'))
+                            && matches!(
+                                data.get(i + 3),
+                                Some(b'8'..=b'9' | b'a'..=b'f' | b'A'..=b'F')
+                            )
+                        {
+                            return false;
+                        }
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'[' | b'{' => {
+                depth += 1;
+                if depth >= 128 {
+                    return false;
+                }
+                i += 1;
+            }
+            b']' | b'}' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            b'-' | b'0'..=b'9' => {
+                let start = i;
+                while i < data.len()
+                    && matches!(data[i], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+                {
+                    i += 1;
+                }
+                if !simd_json_number_compatible(&data[start..i]) {
+                    return false;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    true
+}
+
+#[cfg(feature = "simd-json")]
+fn simd_json_number_compatible(n: &[u8]) -> bool {
+    if n.starts_with(b"-0") {
+        return false;
+    }
+
+    // Integers that don't fit into 64 bits fail to parse and fall back to `serde_json`
+    let Some(e) = n.iter().position(|c| matches!(c, b'.' | b'e' | b'E')) else {
+        return true;
+    };
+
+    // Floats are only rounded exactly by `serde_json` when the significand and the power
+    // of ten it is scaled by are both exactly representable
+    let (mantissa, exponent) = match n.iter().position(|c| matches!(c, b'e' | b'E')) {
+        Some(x) => (&n[..x], &n[x + 1..]),
+        None => (n, &b"0"[..]),
+    };
+    let Some(exponent) = std::str::from_utf8(exponent)
+        .ok()
+        .and_then(|x| x.parse::<i32>().ok())
+    else {
+        return false;
+    };
+    let fraction = if n[e] == b'.' {
+        mantissa.len() - e - 1
+    } else {
+        0
+    };
+    let digits = mantissa
+        .iter()
+        .filter(|c| c.is_ascii_digit())
+        .skip_while(|c| **c == b'0')
+        .count();
+    digits <= 15 && (-22..=22).contains(&(exponent - fraction as i32))
+}
+
+#[cfg(feature = "simd-json")]
+fn simd_json_from_slice<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T, crate::Error> {
+    if simd_json_compatible(data) {
+        // simd-json parses in place, so the input is copied into a mutable buffer first
+        if let Ok(x) = simd_json::serde::from_slice(&mut data.to_vec()) {
+            return Ok(x);
+        }
+    }
+
+    // Errors are always reported by `serde_json` so they match the default `Json` encoding
+    Ok(serde_json::from_slice(data)?)
+}
+
+// Values are still encoded using `serde_json`
+#[cfg(feature = "simd-json")]
+extism_convert::encoding!(pub SimdJson, serde_json::to_vec, simd_json_from_slice);
+
+/// Unparsed JSON
+///
+/// `JsonRaw` is validated when it is decoded, but the document is kept as text so it can be
+/// forwarded to the host, a host function or an HTTP request without being re-serialized.
+/// [JsonRaw::patch] can be used to replace a single value without parsing the rest of the
+/// document. `Json<Box<serde_json::value::RawValue>>` may also be used when only
+/// pass-through is needed.
+#[derive(Debug, Clone)]
+pub struct JsonRaw(String);
+
+impl JsonRaw {
+    /// Validate and wrap a JSON document
+    pub fn new(json: impl Into<String>) -> Result<Self, crate::Error> {
+        let json = json.into();
+        serde_json::from_str::<&serde_json::value::RawValue>(&json)?;
+        Ok(JsonRaw(json))
+    }
+
+    /// Get the JSON text
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+
+    /// Parse the whole document into `T`
+    pub fn to<T: serde::de::DeserializeOwned>(&self) -> Result<T, crate::Error> {
+        Ok(serde_json::from_str(&self.0)?)
+    }
+
+    /// Get the value at `pointer` (RFC 6901, i.e. `/user/emails/0`) without parsing it,
+    /// returns `None` if there is no value at the given location
+    pub fn pointer(&self, pointer: &str) -> Result<Option<&str>, crate::Error> {
+        Ok(self.span(pointer)?.map(|x| &self.0[x]))
+    }
+
+    /// Replace the value at `pointer` (RFC 6901, i.e. `/user/emails/0`) with `value`, only
+    /// the objects and arrays along the path are parsed and the rest of the document is
+    /// copied as-is
+    pub fn patch(
+        &self,
+        pointer: &str,
+        value: &impl serde::Serialize,
+    ) -> Result<Self, crate::Error> {
+        let Some(span) = self.span(pointer)? else {
+            anyhow::bail!("JSON pointer {pointer} does not point to an existing value");
+        };
+        let value = serde_json::to_string(value)?;
+        let mut json = String::with_capacity(self.0.len() - span.len() + value.len());
+        json.push_str(&self.0[..span.start]);
+        json.push_str(&value);
+        json.push_str(&self.0[span.end..]);
+        Ok(JsonRaw(json))
+    }
+
+    fn span(&self, pointer: &str) -> Result<Option<std::ops::Range<usize>>, crate::Error> {
+        use serde_json::value::RawValue;
+
+        let mut current: &RawValue = serde_json::from_str(&self.0)?;
+        if !pointer.is_empty() {
+            let Some(pointer) = pointer.strip_prefix('/') else {
+                anyhow::bail!("Invalid JSON pointer: {pointer}");
+            };
+
+            for token in pointer.split('/') {
+                let token = token.replace("~1", "/").replace("~0", "~");
+                let json = current.get();
+                let next = if json.starts_with('{') {
+                    let object: std::collections::HashMap<String, &RawValue> =
+                        serde_json::from_str(json)?;
+                    object.get(&token).copied()
+                } else if json.starts_with('[') {
+                    let array: Vec<&RawValue> = serde_json::from_str(json)?;
+                    // RFC 6901 array indices are either `0` or digits without a leading zero
+                    let valid = token.bytes().all(|c| c.is_ascii_digit())
+                        && (token == "0" || !token.starts_with('0'));
+                    token
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|_| valid)
+                        .and_then(|i| array.get(i).copied())
+                } else {
+                    None
+                };
+
+                match next {
+                    Some(x) => current = x,
+                    None => return Ok(None),
+                }
+            }
+        }
+
+        let start = current.get().as_ptr() as usize - self.0.as_ptr() as usize;
+        Ok(Some(start..start + current.get().len()))
+    }
+}
+
+impl From<Box<serde_json::value::RawValue>> for JsonRaw {
+    fn from(value: Box<serde_json::value::RawValue>) -> Self {
+        JsonRaw(value.get().to_string())
+    }
+}
+
+impl crate::FromBytesOwned for JsonRaw {
+    fn from_bytes_owned(data: &[u8]) -> Result<Self, crate::Error> {
+        JsonRaw::new(std::str::from_utf8(data)?)
+    }
+}
+
+impl crate::ToBytes<'_> for JsonRaw {
+    type Bytes = String;
+
+    fn to_bytes(&self) -> Result<Self::Bytes, crate::Error> {
+        Ok(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_pointer_array_index() {
+        let json = JsonRaw::new(r#"{"a": {"b/c": [10, 11, 12]}, "~": [1]}"#).unwrap();
+        assert_eq!(json.pointer("/a/b~1c/0").unwrap(), Some("10"));
+        assert_eq!(json.pointer("/a/b~1c/2").unwrap(), Some("12"));
+        assert_eq!(json.pointer("/~0/0").unwrap(), Some("1"));
+        for pointer in [
+            "/a/b~1c/01",
+            "/a/b~1c/00",
+            "/a/b~1c/+1",
+            "/a/b~1c/-1",
+            "/a/b~1c/ 1",
+            "/a/b~1c/",
+            "/a/b~1c/3",
+        ] {
+            assert_eq!(json.pointer(pointer).unwrap(), None, "pointer: {pointer}");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "simd-json"))]
+mod simd_json_tests {
+    use super::*;
+    use extism_convert::{FromBytesOwned, Json, ToBytes};
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Point {
+        x: i64,
+        y: i64,
+    }
+
+    // Decodes `input` using both parsers and checks the results are identical, values are
+    // compared as re-serialized JSON so `-0.0` and `0.0` are distinguished
+    fn check(input: &str) {
+        let simd = simd_json_from_slice::<serde_json::Value>(input.as_bytes());
+        let serde = serde_json::from_slice::<serde_json::Value>(input.as_bytes());
+        match (simd, serde) {
+            (Ok(a), Ok(b)) => assert_eq!(a.to_string(), b.to_string(), "input: {input}"),
+            (Err(a), Err(b)) => assert_eq!(a.to_string(), b.to_string(), "input: {input}"),
+            (a, b) => panic!("input: {input}, simd-json: {a:?}, serde_json: {b:?}"),
+        }
+    }
+
+    #[test]
+    fn prescan() {
+        for input in [
+            r#"{"a": [1, 2.5, "\u00e9"]}"#,
+            "1e22",
+            "123456789012345",
+            "-1",
+            "0",
+        ] {
+            assert!(simd_json_compatible(input.as_bytes()), "input: {input}");
+        }
+        for input in [r#""\ud83d\ude00""#, "-0", "1e23", "1234567890123456.7"] {
+            assert!(!simd_json_compatible(input.as_bytes()), "input: {input}");
+        }
+        assert!(!simd_json_compatible("[".repeat(128).as_bytes()));
+    }
+
+    #[test]
+    fn surrogate_escapes() {
+        check(r#""\ud83d\ude00""#);
+        check(r#""\uD83D\uDE00""#);
+        check(r#"["\ud83d"]"#);
+        check(r#""\ude00\ud83d""#);
+        check(r#"{"\ud83d\ude00": 1}"#);
+        check(r#""\u00e9\\ud83d""#);
+    }
+
+    #[test]
+    fn negative_zero() {
+        check("-0");
+        check("-0.0");
+        check("[-0, 0, -0e0, -0.0e-5]");
+        check(r#"{"a": -0}"#);
+    }
+
+    #[test]
+    fn float_bounds() {
+        for input in [
+            "1e22",
+            "1e23",
+            "1e-22",
+            "1e-23",
+            "123456789012345e22",
+            "123456789012345e-22",
+            "1234567890123456e22",
+            "123456789012345.6",
+            "1234567890123456.7",
+            "0.123456789012345",
+            "0.1234567890123456",
+            "0.000000000000000000000001",
+            "9007199254740993.0",
+            "1.7976931348623157e308",
+            "5e-324",
+            "2.2250738585072014e-308",
+            "1e400",
+        ] {
+            check(input);
+            check(&format!("[{input}, -{input}]"));
+        }
+    }
+
+    #[test]
+    fn large_integers() {
+        for input in [
+            "9223372036854775807",
+            "9223372036854775808",
+            "-9223372036854775808",
+            "-9223372036854775809",
+            "18446744073709551615",
+            "18446744073709551616",
+            "100000000000000000000000000000",
+            "-100000000000000000000000000000",
+        ] {
+            check(input);
+            check(&format!(r#"{{"n": {input}}}"#));
+        }
+    }
+
+    #[test]
+    fn nesting_depth() {
+        for depth in [126, 127, 128, 129, 200] {
+            check(&format!("{}{}", "[".repeat(depth), "]".repeat(depth)));
+            check(&format!(
+                "{}1{}",
+                r#"{"a":"#.repeat(depth),
+                "}".repeat(depth)
+            ));
+        }
+    }
+
+    #[test]
+    fn trailing_garbage() {
+        for input in [
+            "1 2",
+            "{} x",
+            "[1]]",
+            r#""a""b""#,
+            "null,",
+            "true false",
+            "{}\0",
+        ] {
+            check(input);
+        }
+        check("  [1, 2]  \n");
+    }
+
+    #[test]
+    fn invalid_documents() {
+        for input in [
+            "", "[", "{\"a\"}", "[1,]", "01", "1.", "+1", "\"\\x\"", "nul",
+        ] {
+            check(input);
+        }
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let input = br#"{"x": 1, "y": 2, "x": 3}"#;
+        let simd = simd_json_from_slice::<Point>(input).map_err(|e| e.to_string());
+        let serde = serde_json::from_slice::<Point>(input).map_err(|e| e.to_string());
+        assert_eq!(simd, serde);
+        check(std::str::from_utf8(input).unwrap());
+    }
+
+    #[test]
+    fn structs() {
+        let input = br#"{"x": -5, "y": 18446744073709551615}"#;
+        let simd = simd_json_from_slice::<Point>(input).map_err(|e| e.to_string());
+        let serde = serde_json::from_slice::<Point>(input).map_err(|e| e.to_string());
+        assert_eq!(simd, serde);
+
+        let input = br#"{"x": -5, "y": 7}"#;
+        assert_eq!(
+            simd_json_from_slice::<Point>(input).unwrap(),
+            Point { x: -5, y: 7 }
+        );
+    }
+    #[test]
+    fn simd_json_encoding() {
+        let SimdJson(point) = SimdJson::<Point>::from_bytes_owned(br#"{"x": 1, "y": 2}"#).unwrap();
+        assert_eq!(point, Point { x: 1, y: 2 });
+        assert_eq!(
+            SimdJson(vec![1, 2]).to_bytes().unwrap(),
+            Json(vec![1, 2]).to_bytes().unwrap()
+        );
+    }
+}
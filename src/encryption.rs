@@ -0,0 +1,359 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::Aes256Gcm;
+use base64::Engine;
+use chacha20poly1305::ChaCha20Poly1305;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::*;
+
+/// The prefix used by encrypted values
+pub const PREFIX: &str = "enc:v1:";
+
+/// The conventional config key for a base64 encoded [EncryptionKey]
+pub const ENCRYPTION_KEY_CONFIG: &str = "encryption_key";
+
+/// The length of an [EncryptionKey] in bytes
+pub const KEY_LEN: usize = 32;
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// The AEAD cipher used to encrypt a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl Cipher {
+    fn id(self) -> u8 {
+        match self {
+            Cipher::Aes256Gcm => 1,
+            Cipher::ChaCha20Poly1305 => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, Error> {
+        match id {
+            1 => Ok(Cipher::Aes256Gcm),
+            2 => Ok(Cipher::ChaCha20Poly1305),
+            _ => anyhow::bail!("Unknown cipher id {id} in encrypted value"),
+        }
+    }
+}
+
+/// A 256-bit key used to encrypt and decrypt values
+///
+/// The key bytes are zeroed when the `EncryptionKey` is dropped.
+pub struct EncryptionKey(Secret<Vec<u8>>);
+
+impl EncryptionKey {
+    /// Create a new key from raw bytes, `key` must be [KEY_LEN] bytes long
+    pub fn new(mut key: Vec<u8>) -> Result<Self, Error> {
+        if key.len() != KEY_LEN {
+            let len = key.len();
+            key.zeroize();
+            anyhow::bail!("Invalid encryption key length {len}, expected {KEY_LEN} bytes");
+        }
+        Ok(EncryptionKey(Secret::new(key)))
+    }
+
+    /// Load a base64 encoded key from the config item `name`
+    pub fn from_config(name: impl AsRef<str>) -> Result<Self, Error> {
+        let name = name.as_ref();
+        let Some(value) = config::get_secret(name)? else {
+            anyhow::bail!("Encryption key is not set, expected config key {name:?}");
+        };
+        let key = base64::engine::general_purpose::STANDARD
+            .decode(value.expose().trim())
+            .map_err(|_| anyhow::anyhow!("Encryption key {name:?} is not valid base64"))?;
+        Self::new(key)
+    }
+
+    /// Load a key using a host function that returns the raw key bytes
+    ///
+    /// ```rust,ignore
+    /// #[host_fn]
+    /// extern "ExtismHost" {
+    ///     fn get_encryption_key() -> Vec<u8>;
+    /// }
+    ///
+    /// let key = EncryptionKey::from_host_fn(|| unsafe { get_encryption_key() })?;
+    /// ```
+    pub fn from_host_fn(f: impl FnOnce() -> Result<Vec<u8>, Error>) -> Result<Self, Error> {
+        Self::new(f()?)
+    }
+
+    /// An identifier for the key, derived from the key so it's safe to store alongside
+    /// encrypted values
+    pub fn id(&self) -> [u8; 8] {
+        let mut id = [0; 8];
+        id.copy_from_slice(&self.subkey(b"extism-pdk key id").expose()[..8]);
+        id
+    }
+
+    /// Derives an independent key for a single purpose, `HMAC-SHA256(key, label)`, so the key
+    /// used to encrypt values is never used for anything else
+    fn subkey(&self, label: &[u8]) -> Secret<Vec<u8>> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.0.expose()).unwrap();
+        mac.update(label);
+        Secret::new(mac.finalize().into_bytes().to_vec())
+    }
+
+    fn cipher_key(&self, cipher: Cipher) -> Secret<Vec<u8>> {
+        match cipher {
+            Cipher::Aes256Gcm => self.subkey(b"extism-pdk aes-256-gcm"),
+            Cipher::ChaCha20Poly1305 => self.subkey(b"extism-pdk chacha20-poly1305"),
+        }
+    }
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey(***)")
+    }
+}
+
+/// Returns true if `value` uses the `enc:v1:` format
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// Encrypt `plaintext` into an `enc:v1:<base64>` string
+///
+/// The decoded payload is `cipher id (1 byte) || nonce (12 bytes) || ciphertext || tag`, and
+/// `aad` is authenticated along with the ciphertext.
+///
+/// Encryption is deterministic: since plug-ins don't have access to a random number generator
+/// the nonce is the first 12 bytes of an HMAC-SHA256 of `aad` and `plaintext`, so encrypting
+/// the same value with the same `aad` twice produces the same output, which reveals when two
+/// values are equal. A nonce is only reused for identical inputs, so it never encrypts two
+/// different messages. The nonce, the cipher and [EncryptionKey::id] each use their own key,
+/// derived from the [EncryptionKey] using `HMAC-SHA256(key, label)` with the labels
+/// `extism-pdk nonce`, `extism-pdk aes-256-gcm`, `extism-pdk chacha20-poly1305` and
+/// `extism-pdk key id`.
+pub fn encrypt(
+    cipher: Cipher,
+    key: &EncryptionKey,
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<String, Error> {
+    let data = seal(cipher, key, aad, plaintext)?;
+    Ok(format!(
+        "{PREFIX}{}",
+        base64::engine::general_purpose::STANDARD.encode(data)
+    ))
+}
+
+/// Decrypt an `enc:v1:<base64>` string created by [encrypt]
+///
+/// Returns an error if the value is malformed, or if the ciphertext or `aad` has been
+/// modified.
+pub fn decrypt(key: &EncryptionKey, aad: &[u8], value: &str) -> Result<Secret<Vec<u8>>, Error> {
+    let Some(encoded) = value.strip_prefix(PREFIX) else {
+        anyhow::bail!("Value is not encrypted, expected {PREFIX:?} prefix");
+    };
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| anyhow::anyhow!("Encrypted value is not valid base64"))?;
+    Ok(Secret::new(open(key, aad, &data)?))
+}
+
+/// Encrypt `plaintext` into the binary payload used by [encrypt]
+pub(crate) fn seal(
+    cipher: Cipher,
+    key: &EncryptionKey,
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.subkey(b"extism-pdk nonce").expose())?;
+    mac.update(&(aad.len() as u64).to_le_bytes());
+    mac.update(aad);
+    mac.update(plaintext);
+    let nonce = mac.finalize().into_bytes();
+    let nonce = &nonce[..NONCE_LEN];
+
+    let payload = Payload {
+        msg: plaintext,
+        aad,
+    };
+    let cipher_key = key.cipher_key(cipher);
+    let ciphertext = match cipher {
+        Cipher::Aes256Gcm => {
+            Aes256Gcm::new_from_slice(cipher_key.expose())?.encrypt(nonce.into(), payload)
+        }
+        Cipher::ChaCha20Poly1305 => {
+            ChaCha20Poly1305::new_from_slice(cipher_key.expose())?.encrypt(nonce.into(), payload)
+        }
+    }
+    .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+
+    let mut data = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+    data.push(cipher.id());
+    data.extend_from_slice(nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+/// Decrypt a binary payload created by [seal]
+pub(crate) fn open(key: &EncryptionKey, aad: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.len() < 1 + NONCE_LEN + TAG_LEN {
+        anyhow::bail!("Encrypted value is too short");
+    }
+
+    let cipher = Cipher::from_id(data[0])?;
+    let nonce = &data[1..1 + NONCE_LEN];
+    let payload = Payload {
+        msg: &data[1 + NONCE_LEN..],
+        aad,
+    };
+    let cipher_key = key.cipher_key(cipher);
+    match cipher {
+        Cipher::Aes256Gcm => {
+            Aes256Gcm::new_from_slice(cipher_key.expose())?.decrypt(nonce.into(), payload)
+        }
+        Cipher::ChaCha20Poly1305 => {
+            ChaCha20Poly1305::new_from_slice(cipher_key.expose())?.decrypt(nonce.into(), payload)
+        }
+    }
+    .map_err(|_| anyhow::anyhow!("Unable to decrypt value: authentication failed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CIPHERS: [Cipher; 2] = [Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305];
+
+    fn key(n: u8) -> EncryptionKey {
+        EncryptionKey::new(vec![n; KEY_LEN]).unwrap()
+    }
+
+    fn reseal(value: &str, f: impl FnOnce(&mut Vec<u8>)) -> String {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let mut data = engine.decode(value.strip_prefix(PREFIX).unwrap()).unwrap();
+        f(&mut data);
+        format!("{PREFIX}{}", engine.encode(data))
+    }
+
+    #[test]
+    fn round_trip() {
+        for cipher in CIPHERS {
+            let value = encrypt(cipher, &key(1), b"db_password", b"hunter2").unwrap();
+            assert!(is_encrypted(&value));
+            let plaintext = decrypt(&key(1), b"db_password", &value).unwrap();
+            assert_eq!(plaintext.expose(), b"hunter2");
+
+            let data = seal(cipher, &key(1), b"", b"").unwrap();
+            assert_eq!(data.len(), 1 + NONCE_LEN + TAG_LEN);
+            assert_eq!(open(&key(1), b"", &data).unwrap(), b"");
+        }
+    }
+
+    #[test]
+    fn deterministic() {
+        for cipher in CIPHERS {
+            let a = encrypt(cipher, &key(1), b"a", b"value").unwrap();
+            assert_eq!(a, encrypt(cipher, &key(1), b"a", b"value").unwrap());
+            assert_ne!(a, encrypt(cipher, &key(1), b"b", b"value").unwrap());
+            assert_ne!(a, encrypt(cipher, &key(1), b"a", b"other").unwrap());
+            assert_ne!(a, encrypt(cipher, &key(2), b"a", b"value").unwrap());
+        }
+    }
+
+    #[test]
+    fn subkeys() {
+        let key = key(1);
+        let subkeys = [
+            key.subkey(b"extism-pdk nonce"),
+            key.cipher_key(Cipher::Aes256Gcm),
+            key.cipher_key(Cipher::ChaCha20Poly1305),
+            key.subkey(b"extism-pdk key id"),
+        ];
+        for (i, a) in subkeys.iter().enumerate() {
+            assert_ne!(a.expose(), key.0.expose());
+            for b in &subkeys[i + 1..] {
+                assert_ne!(a.expose(), b.expose());
+            }
+        }
+        assert_eq!(&subkeys[3].expose()[..8], &key.id());
+    }
+
+    #[test]
+    fn flipped_ciphertext_bit() {
+        for cipher in CIPHERS {
+            let value = encrypt(cipher, &key(1), b"k", b"secret value").unwrap();
+            let value = reseal(&value, |data| data[1 + NONCE_LEN] ^= 1);
+            assert!(decrypt(&key(1), b"k", &value).is_err());
+        }
+    }
+
+    #[test]
+    fn changed_tag() {
+        for cipher in CIPHERS {
+            let value = encrypt(cipher, &key(1), b"k", b"secret value").unwrap();
+            let value = reseal(&value, |data| *data.last_mut().unwrap() ^= 0x80);
+            assert!(decrypt(&key(1), b"k", &value).is_err());
+        }
+    }
+
+    #[test]
+    fn changed_nonce() {
+        for cipher in CIPHERS {
+            let value = encrypt(cipher, &key(1), b"k", b"secret value").unwrap();
+            let value = reseal(&value, |data| data[1] ^= 1);
+            assert!(decrypt(&key(1), b"k", &value).is_err());
+        }
+    }
+
+    #[test]
+    fn wrong_aad() {
+        for cipher in CIPHERS {
+            let value = encrypt(cipher, &key(1), b"db_password", b"secret").unwrap();
+            assert!(decrypt(&key(1), b"api_key", &value).is_err());
+            assert!(decrypt(&key(1), b"", &value).is_err());
+        }
+    }
+
+    #[test]
+    fn wrong_key() {
+        for cipher in CIPHERS {
+            let value = encrypt(cipher, &key(1), b"k", b"secret").unwrap();
+            assert!(decrypt(&key(2), b"k", &value).is_err());
+        }
+    }
+
+    #[test]
+    fn wrong_cipher_id() {
+        let value = encrypt(Cipher::Aes256Gcm, &key(1), b"k", b"secret").unwrap();
+        let swapped = reseal(&value, |data| data[0] = Cipher::ChaCha20Poly1305.id());
+        assert!(decrypt(&key(1), b"k", &swapped).is_err());
+
+        for id in [0, 3, 0xff] {
+            let unknown = reseal(&value, |data| data[0] = id);
+            let err = decrypt(&key(1), b"k", &unknown).unwrap_err();
+            assert!(err.to_string().contains("Unknown cipher id"), "{err}");
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        for cipher in CIPHERS {
+            let data = seal(cipher, &key(1), b"k", b"secret").unwrap();
+            for len in 0..data.len() {
+                assert!(open(&key(1), b"k", &data[..len]).is_err(), "length {len}");
+            }
+            assert!(open(&key(1), b"k", &data).is_ok());
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(decrypt(&key(1), b"k", "plaintext").is_err());
+        assert!(decrypt(&key(1), b"k", "enc:v1:not base64!").is_err());
+        assert!(decrypt(&key(1), b"k", "enc:v1:").is_err());
+        assert!(EncryptionKey::new(vec![0; KEY_LEN - 1]).is_err());
+    }
+}
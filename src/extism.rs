@@ -80,6 +80,32 @@ pub unsafe fn load_input() -> Vec<u8> {
     data
 }
 
+/// Loads part of the input from the host into a byte slice, starting `offs` bytes
+/// into the input. The caller must make sure that `offs + data.len()` does not
+/// exceed `input_length()`.
+///
+/// # Arguments
+///
+/// * `offs` - The offset into the input to start reading from
+/// * `data` - The byte slice to fill with input data
+pub unsafe fn load_input_range(offs: u64, data: &mut [u8]) {
+    let len = data.len();
+    let chunk_count = len >> 3;
+
+    let mut_ptr = data.as_mut_ptr() as *mut u64;
+    for chunk_idx in 0..chunk_count {
+        let x = input_load_u64(offs + (chunk_idx << 3) as u64);
+        mut_ptr.add(chunk_idx).write_unaligned(x);
+    }
+
+    let remainder = len & 7;
+    let remainder_offset = chunk_count << 3;
+    #[allow(clippy::needless_range_loop)]
+    for index in remainder_offset..(remainder + remainder_offset) {
+        data[index] = input_load_u8(offs + index as u64);
+    }
+}
+
 /// Stores a byte array into Extism's memory.
 /// Only use this after considering []
 ///
@@ -28,10 +28,15 @@ impl HttpResponse {
     }
 
     pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, Error> {
-        let x = serde_json::from_slice(&self.body())?;
+        let Json(x) = self.to()?;
         Ok(x)
     }
 
+    /// Decode the response body using any `FromBytesOwned` encoding, i.e. `Cbor<T>`
+    pub fn to<T: FromBytesOwned>(&self) -> Result<T, Error> {
+        self.memory.to()
+    }
+
     pub fn headers(&self) -> &HashMap<String, String> {
         &self.headers
     }
@@ -0,0 +1,45 @@
+use std::cell::RefCell;
+
+use crate::*;
+
+thread_local! {
+    static RESULT: RefCell<Option<Result<(), (String, i32)>>> = const { RefCell::new(None) };
+}
+
+/// Runs a `#[plugin_init]` function once, later calls return the saved result
+///
+/// The error message and return code are saved, so a failed initialization is reported by
+/// every later call.
+pub fn run(f: fn() -> FnResult<()>) -> Result<(), (String, i32)> {
+    if let Some(res) = RESULT.with_borrow(|r| r.clone()) {
+        return res;
+    }
+    let res = f().map_err(|rc| (format!("{:?}", rc.0), rc.1));
+    RESULT.set(Some(res.clone()));
+    res
+}
+
+/// Reports an initialization error using `error_set` and returns the status code
+pub fn report(err: &(String, i32)) -> i32 {
+    let mem = Memory::from_bytes(&err.0).unwrap();
+    unsafe {
+        extism::error_set(mem.offset());
+    }
+    err.1
+}
+
+/// `plugin_fn` calls `(&Hook).__extism_plugin_init()` with both [DefaultInit] and the crate
+/// root in scope. `#[plugin_init]` implements a trait with the same method for `Hook` at the
+/// crate root, which is preferred by method resolution over the [DefaultInit] implementation
+/// for `&Hook`, so the initializer is only called when one is defined.
+pub struct Hook;
+
+pub trait DefaultInit {
+    fn __extism_plugin_init(&self) -> Result<(), (String, i32)>;
+}
+
+impl DefaultInit for &Hook {
+    fn __extism_plugin_init(&self) -> Result<(), (String, i32)> {
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use crate::*;
+
+/// A key-value store
+///
+/// `KvStore` lets business logic be written against a persistence interface instead of
+/// calling [var] directly, so the same code can use plug-in variables, wasm memory or a
+/// store provided by the host. Use [KvStoreExt] to read and write encoded values.
+pub trait KvStore {
+    /// Gets the value for `key`
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Sets the value for `key`
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Error>;
+
+    /// Removes `key` from the store
+    fn delete(&mut self, key: &str) -> Result<(), Error>;
+
+    /// Lists the keys that start with `prefix`, in sorted order
+    fn scan_prefix(&self, prefix: &str) -> Result<Vec<String>, Error>;
+}
+
+/// Encoded reads and writes for every [KvStore]
+pub trait KvStoreExt: KvStore {
+    /// Gets the value for `key` and decodes it
+    fn get_as<T: FromBytesOwned>(&self, key: &str) -> Result<Option<T>, Error> {
+        match self.get(key)? {
+            Some(data) => Ok(Some(T::from_bytes_owned(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Encodes `value` and stores it at `key`
+    fn set_as<'a, T: ToBytes<'a>>(&mut self, key: &str, value: T) -> Result<(), Error> {
+        self.set(key, value.to_bytes()?.as_ref())
+    }
+}
+
+impl<S: KvStore + ?Sized> KvStoreExt for S {}
+
+/// A [KvStore] backed by plug-in variables
+///
+/// Keys are stored in a [var::Namespace], which tracks them in an index variable so they can
+/// be scanned.
+#[derive(Debug, Clone)]
+pub struct VarStore(var::Namespace);
+
+impl VarStore {
+    /// Create a store using the [var::Namespace] `name`, returns an error if `name` contains
+    /// `/` or `#`
+    pub fn new(name: impl Into<String>) -> Result<Self, Error> {
+        Ok(VarStore(var::Namespace::new(name)?))
+    }
+}
+
+impl KvStore for VarStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.0.get(key)
+    }
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.0.set(key, value)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), Error> {
+        self.0.remove(key)
+    }
+
+    fn scan_prefix(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let mut keys: Vec<String> = self
+            .0
+            .keys()?
+            .into_iter()
+            .filter(|k| k.starts_with(prefix))
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+/// A [KvStore] kept in wasm memory, for tests and data that doesn't need to outlive the store
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore(BTreeMap<String, Vec<u8>>);
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl KvStore for MemoryStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.0.get(key).cloned())
+    }
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.0.insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), Error> {
+        self.0.remove(key);
+        Ok(())
+    }
+
+    fn scan_prefix(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        Ok(self
+            .0
+            .range(prefix.to_string()..)
+            .map(|(k, _)| k)
+            .take_while(|k| k.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+}
+
+/// The host functions used by [HostStore]
+///
+/// The functions are imported using [host_fn] and are expected to follow this convention:
+///
+/// ```rust,ignore
+/// #[host_fn]
+/// extern "ExtismHost" {
+///     // Returns the value, or an empty output when the key isn't set
+///     fn kv_get(key: String) -> Vec<u8>;
+///     fn kv_set(key: String, value: Vec<u8>);
+///     fn kv_delete(key: String);
+///     // Returns a JSON array of the keys that start with `prefix`
+///     fn kv_scan(prefix: String) -> Json<Vec<String>>;
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct HostFns {
+    pub get: unsafe fn(String) -> Result<Vec<u8>, Error>,
+    pub set: unsafe fn(String, Vec<u8>) -> Result<(), Error>,
+    pub delete: unsafe fn(String) -> Result<(), Error>,
+    pub scan: unsafe fn(String) -> Result<Json<Vec<String>>, Error>,
+}
+
+/// A [KvStore] backed by host functions, see [HostFns]
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// let mut store = unsafe {
+///     kv::HostStore::new(kv::HostFns {
+///         get: kv_get,
+///         set: kv_set,
+///         delete: kv_delete,
+///         scan: kv_scan,
+///     })
+/// };
+/// store.set_as("greeting", "Hello")?;
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct HostStore(HostFns);
+
+impl HostStore {
+    /// Create a new `HostStore`
+    ///
+    /// # Safety
+    ///
+    /// The host functions are called by safe methods, so the caller must ensure they're
+    /// provided by the host and follow the convention described by [HostFns].
+    pub unsafe fn new(fns: HostFns) -> Self {
+        HostStore(fns)
+    }
+}
+
+impl KvStore for HostStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let value = unsafe { (self.0.get)(key.to_string())? };
+        if value.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(value))
+    }
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        unsafe { (self.0.set)(key.to_string(), value.to_vec()) }
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), Error> {
+        unsafe { (self.0.delete)(key.to_string()) }
+    }
+
+    fn scan_prefix(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let Json(mut keys) = unsafe { (self.0.scan)(prefix.to_string())? };
+        keys.sort();
+        Ok(keys)
+    }
+}
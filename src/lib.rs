@@ -3,10 +3,16 @@
 #[cfg(target_arch = "wasm32")]
 pub use std::arch::wasm32::v128;
 
+mod encoding;
 mod macros;
 
 pub mod extism;
+#[doc(hidden)]
+pub mod init;
 pub mod memory;
+mod secret;
+#[doc(hidden)]
+pub mod state;
 mod to_memory;
 
 /// Functions to read plug-in config
@@ -15,17 +21,71 @@ pub mod config;
 /// Functions to manipulate plug-in variables
 pub mod var;
 
+/// Key-value store abstraction
+pub mod kv;
+
 #[cfg(feature = "http")]
 /// Types and functions for making HTTP requests
 pub mod http;
 
+#[cfg(feature = "arrow")]
+/// Apache Arrow IPC encoding and input streaming
+pub mod arrow;
+
+#[cfg(feature = "signed")]
+/// HMAC signed inputs and outputs
+pub mod signed;
+
+#[cfg(feature = "encryption")]
+/// AES-GCM and ChaCha20-Poly1305 encrypted values
+pub mod encryption;
+
 pub use anyhow::Error;
+pub use config::PluginConfig;
 pub use extism_convert::*;
 pub use extism_convert::{FromBytes, FromBytesOwned, ToBytes};
-pub use extism_pdk_derive::{host_fn, plugin_fn, shared_fn};
+pub use extism_pdk_derive::{
+    host_fn, plugin_fn, plugin_init, plugin_state, shared_fn, PluginConfig,
+};
+
 pub use memory::{ManagedMemory, Memory, MemoryPointer};
+pub use secret::Secret;
+pub use state::PluginState;
 pub use to_memory::ToMemory;
 
+pub use encoding::{Base64, Base64NoPad, Base64Url, Base64UrlNoPad, Hex, JsonRaw};
+
+#[cfg(feature = "simd-json")]
+/// JSON encoding, decoded using `simd-json`. To use wasm SIMD instructions the plugin
+/// should be built with `-C target-feature=+simd128`
+pub use encoding::SimdJson;
+
+#[cfg(feature = "cbor")]
+/// CBOR encoding
+pub use encoding::Cbor;
+
+#[cfg(feature = "postcard")]
+/// Postcard encoding
+pub use encoding::Postcard;
+
+#[cfg(feature = "bincode")]
+/// Bincode encoding, using the standard bincode configuration
+pub use encoding::Bincode;
+
+#[cfg(feature = "yaml")]
+/// YAML encoding
+pub use encoding::Yaml;
+
+#[cfg(feature = "toml")]
+/// TOML encoding
+pub use encoding::Toml;
+
+#[cfg(feature = "gzip")]
+pub use encoding::Gzip;
+
+#[cfg(feature = "zstd")]
+pub use encoding::Zstd;
+
 #[cfg(feature = "http")]
 /// HTTP request type
 pub use extism_manifest::HttpRequest;
@@ -34,6 +94,18 @@ pub use extism_manifest::HttpRequest;
 /// HTTP response type
 pub use http::HttpResponse;
 
+#[cfg(feature = "arrow")]
+/// Arrow IPC encoding
+pub use arrow::ArrowIpc;
+
+#[cfg(feature = "signed")]
+/// HMAC signed encoding
+pub use signed::Signed;
+
+#[cfg(feature = "encryption")]
+/// Encryption keys for `enc:v1:` values
+pub use encryption::EncryptionKey;
+
 /// The return type of a plugin function
 pub type FnResult<T> = Result<T, WithReturnCode<Error>>;
 
@@ -65,9 +137,6 @@ impl LogLevel {
 /// Re-export of `serde_json`
 pub use serde_json as json;
 
-/// Base64 string
-pub struct Base64(pub String);
-
 /// Get input bytes from host
 pub fn input_bytes() -> Vec<u8> {
     unsafe { extism::load_input() }
@@ -79,6 +148,47 @@ pub fn input<T: FromBytesOwned>() -> Result<T, Error> {
     T::from_bytes_owned(&data)
 }
 
+/// Get a reader over the input from the host, this can be used to decode large inputs
+/// incrementally instead of copying the whole input into memory first
+pub fn input_reader() -> InputReader {
+    InputReader::default()
+}
+
+/// Reads input from the host, see [input_reader]
+pub struct InputReader {
+    offset: u64,
+    length: u64,
+}
+
+impl Default for InputReader {
+    fn default() -> Self {
+        InputReader {
+            offset: 0,
+            length: unsafe { extism::input_length() },
+        }
+    }
+}
+
+impl std::io::Read for InputReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = std::cmp::min(buf.len() as u64, self.length - self.offset) as usize;
+        unsafe { extism::load_input_range(self.offset, &mut buf[..n]) };
+        self.offset += n as u64;
+        Ok(n)
+    }
+}
+
+/// Get the return code for an error returned when decoding `plugin_fn` input
+#[doc(hidden)]
+pub fn input_error_status(_e: &Error) -> i32 {
+    #[cfg(feature = "signed")]
+    if _e.downcast_ref::<signed::InvalidSignature>().is_some() {
+        return signed::INVALID_SIGNATURE;
+    }
+
+    -1
+}
+
 /// Set output for host
 pub fn output<T: ToMemory>(data: T) -> Result<(), Error> {
     let data = data.to_memory()?;
@@ -68,3 +68,13 @@ macro_rules! set_var {
         $crate::var::set($k, &format!($($arg)+))
     };
 }
+
+#[macro_export]
+macro_rules! increment_var {
+    ($k:expr) => {
+        $crate::var::increment($k, 1)
+    };
+    ($k:expr, $by:expr) => {
+        $crate::var::increment($k, $by)
+    };
+}
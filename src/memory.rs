@@ -142,6 +142,14 @@ impl Memory {
     pub fn free(self) {
         internal::memory_free(self.0)
     }
+
+    /// Overwrite a memory block with zeros and free it, this should be used for memory
+    /// that contains sensitive data
+    pub fn zero_and_free(self) {
+        let zeros = vec![0u8; self.len()];
+        internal::store(self.0, zeros);
+        self.free()
+    }
 }
 
 impl From<Memory> for () {
@@ -0,0 +1,43 @@
+use zeroize::Zeroize;
+
+/// A sensitive value, such as an API key
+///
+/// `Debug` and `Display` print `***` instead of the value, so a `Secret` can be included in log
+/// messages and errors without leaking it. The value is zeroed when the `Secret` is dropped.
+/// Use [Secret::expose] to access the value.
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+    pub fn new(x: T) -> Self {
+        Secret(x)
+    }
+
+    /// Access the secret value
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(x: T) -> Self {
+        Secret(x)
+    }
+}
+
+impl<T: Zeroize> std::fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl<T: Zeroize> std::fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize()
+    }
+}
@@ -0,0 +1,199 @@
+use std::marker::PhantomData;
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::*;
+
+/// The config key used to look up the HMAC-SHA256 key for [Signed] values by default
+pub const SIGNING_KEY_CONFIG: &str = "signing_key";
+
+/// The return code used by `plugin_fn` when a [Signed] input fails verification
+pub const INVALID_SIGNATURE: i32 = -2;
+
+/// The error returned when a [Signed] value fails verification
+#[derive(Debug)]
+pub struct InvalidSignature;
+
+impl std::fmt::Display for InvalidSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Invalid signature")
+    }
+}
+
+impl std::error::Error for InvalidSignature {}
+
+/// Names the config item that holds the HMAC-SHA256 key for a [Signed] value
+///
+/// ```rust,ignore
+/// struct WebhookKey;
+///
+/// impl signed::SigningKey for WebhookKey {
+///     const CONFIG: &'static str = "webhook_secret";
+/// }
+///
+/// #[plugin_fn]
+/// pub fn webhook(Signed(event, ..): Signed<Event, Json<Event>, WebhookKey>) -> FnResult<()> {
+///     Ok(())
+/// }
+/// ```
+pub trait SigningKey {
+    const CONFIG: &'static str;
+}
+
+/// Reads the signing key from the [SIGNING_KEY_CONFIG] config item
+#[derive(Debug)]
+pub struct DefaultSigningKey;
+
+impl SigningKey for DefaultSigningKey {
+    const CONFIG: &'static str = SIGNING_KEY_CONFIG;
+}
+
+/// The encoding of a [Signed] payload, implemented for `Vec<u8>`, `String` and the serde
+/// based encodings, i.e. `Json<T>` encodes a `T` payload
+pub trait PayloadEncoding<T> {
+    fn encode(value: &T) -> Result<Vec<u8>, Error>;
+    fn decode(data: &[u8]) -> Result<T, Error>;
+}
+
+impl PayloadEncoding<Vec<u8>> for Vec<u8> {
+    fn encode(value: &Vec<u8>) -> Result<Vec<u8>, Error> {
+        Ok(value.clone())
+    }
+
+    fn decode(data: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(data.to_vec())
+    }
+}
+
+impl PayloadEncoding<String> for String {
+    fn encode(value: &String) -> Result<Vec<u8>, Error> {
+        Ok(value.clone().into_bytes())
+    }
+
+    fn decode(data: &[u8]) -> Result<String, Error> {
+        Ok(String::from_utf8(data.to_vec())?)
+    }
+}
+
+macro_rules! serde_payload_encoding {
+    ($($(#[$attr:meta])* $($name:ident)::+;)*) => {
+        $(
+            $(#[$attr])*
+            impl<T: serde::Serialize + serde::de::DeserializeOwned> PayloadEncoding<T>
+                for $($name)::+<T>
+            {
+                fn encode(value: &T) -> Result<Vec<u8>, Error> {
+                    $($name)::+(value).to_bytes()
+                }
+
+                fn decode(data: &[u8]) -> Result<T, Error> {
+                    Ok($($name)::+::<T>::from_bytes_owned(data)?.0)
+                }
+            }
+        )*
+    };
+}
+
+// `$name<&T>` is used to encode a borrowed payload, which serializes the same as `$name<T>`
+serde_payload_encoding! {
+    extism_convert::Json;
+    #[cfg(feature = "simd-json")]
+    encoding::SimdJson;
+    #[cfg(feature = "msgpack")]
+    extism_convert::Msgpack;
+    #[cfg(feature = "cbor")]
+    encoding::Cbor;
+    #[cfg(feature = "postcard")]
+    encoding::Postcard;
+    #[cfg(feature = "bincode")]
+    encoding::Bincode;
+    #[cfg(feature = "yaml")]
+    encoding::Yaml;
+    #[cfg(feature = "toml")]
+    encoding::Toml;
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Envelope {
+    payload: String,
+    signature: String,
+}
+
+/// HMAC-SHA256 signed values
+///
+/// `Signed<T, E, K>` expects a JSON envelope of the form
+/// `{"payload": "<base64>", "signature": "<hex>"}`, where the signature is the HMAC-SHA256 of
+/// the decoded payload. The payload is decoded into `T` using `E`, which defaults to
+/// `Json<T>`, and the key is read from the config item named by `K`, which defaults to
+/// [SIGNING_KEY_CONFIG]. When a `plugin_fn` input fails verification the function returns
+/// [INVALID_SIGNATURE] before its body is called. Encoding a `Signed` value produces an
+/// envelope signed using the same key.
+///
+/// Inputs can be destructured using `Signed(value, ..)`, and outputs created using
+/// [Signed::new].
+pub struct Signed<T, E = Json<T>, K = DefaultSigningKey>(pub T, pub PhantomData<fn() -> (E, K)>);
+
+impl<T, E, K> Signed<T, E, K> {
+    pub fn new(data: T) -> Self {
+        Signed(data, PhantomData)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, E, K> From<T> for Signed<T, E, K> {
+    fn from(data: T) -> Self {
+        Self::new(data)
+    }
+}
+
+impl<T: std::fmt::Debug, E, K> std::fmt::Debug for Signed<T, E, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Signed").field(&self.0).finish()
+    }
+}
+
+fn mac<K: SigningKey>() -> Result<Hmac<Sha256>, Error> {
+    let Some(key) = config::get(K::CONFIG)? else {
+        anyhow::bail!(
+            "Signing key is not set, expected config key {:?}",
+            K::CONFIG
+        );
+    };
+    Ok(Hmac::new_from_slice(key.as_bytes())?)
+}
+
+impl<T, E: PayloadEncoding<T>, K: SigningKey> FromBytesOwned for Signed<T, E, K> {
+    fn from_bytes_owned(data: &[u8]) -> Result<Self, Error> {
+        let envelope: Envelope = serde_json::from_slice(data)?;
+        let payload = base64::engine::general_purpose::STANDARD.decode(envelope.payload)?;
+        let signature = hex::decode(envelope.signature).map_err(|_| InvalidSignature)?;
+
+        let mut mac = mac::<K>()?;
+        mac.update(&payload);
+        mac.verify_slice(&signature).map_err(|_| InvalidSignature)?;
+
+        Ok(Signed::new(E::decode(&payload)?))
+    }
+}
+
+impl<T, E: PayloadEncoding<T>, K: SigningKey> ToBytes<'_> for Signed<T, E, K> {
+    type Bytes = Vec<u8>;
+
+    fn to_bytes(&self) -> Result<Self::Bytes, Error> {
+        let payload = E::encode(&self.0)?;
+
+        let mut mac = mac::<K>()?;
+        mac.update(&payload);
+
+        let envelope = Envelope {
+            payload: base64::engine::general_purpose::STANDARD.encode(&payload),
+            signature: hex::encode(mac.finalize().into_bytes()),
+        };
+        Ok(serde_json::to_vec(&envelope)?)
+    }
+}
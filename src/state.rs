@@ -0,0 +1,69 @@
+use crate::*;
+
+/// State that persists across calls, stored in a variable
+///
+/// Use the `#[plugin_state]` attribute to implement this trait, then add a `&mut` parameter
+/// with the state type to a `#[plugin_fn]`. The state is loaded before the function is called,
+/// using `Default` when the variable isn't set, and saved when it returns if it was modified.
+///
+/// ```rust,ignore
+/// #[plugin_state(key = "counter", encoding = Json)]
+/// #[derive(Default, serde::Serialize, serde::Deserialize)]
+/// pub struct Counter {
+///     count: u64,
+/// }
+///
+/// #[plugin_fn]
+/// pub fn count(state: &mut Counter) -> FnResult<u64> {
+///     state.count += 1;
+///     Ok(state.count)
+/// }
+/// ```
+pub trait PluginState: Default + Sized {
+    /// The variable used to store the state
+    const KEY: &'static str;
+
+    fn decode(data: &[u8]) -> Result<Self, Error>;
+
+    fn encode(&self) -> Result<Vec<u8>, Error>;
+
+    /// Loads the state, or returns the default state if it hasn't been saved yet
+    fn load() -> Result<Self, Error> {
+        match var::get_bytes(Self::KEY)? {
+            Some(data) => Self::decode(&data),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Saves the state
+    fn save(&self) -> Result<(), Error> {
+        var::set(Self::KEY, self.encode()?)
+    }
+}
+
+/// Loaded state that keeps track of its encoded value, so it's only saved when modified
+#[doc(hidden)]
+pub struct StateCell<T> {
+    value: T,
+    original: Vec<u8>,
+}
+
+impl<T: PluginState> StateCell<T> {
+    pub fn load() -> Result<Self, Error> {
+        let value = T::load()?;
+        let original = value.encode()?;
+        Ok(StateCell { value, original })
+    }
+
+    pub fn value(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    pub fn save(self) -> Result<(), Error> {
+        let data = self.value.encode()?;
+        if data != self.original {
+            var::set(T::KEY, data)?;
+        }
+        Ok(())
+    }
+}
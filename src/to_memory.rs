@@ -1,13 +1,19 @@
 use crate::*;
 
-use base64::Engine;
-
 pub trait ToMemory {
     fn to_memory(&self) -> Result<Memory, Error>;
 
     fn status(&self) -> i32 {
         0
     }
+
+    /// Copies the value into wasm memory, used when a value is kept in the plug-in instead
+    /// of being passed to the host. Values that implement `ToBytes` don't allocate kernel
+    /// memory.
+    #[doc(hidden)]
+    fn to_bytes_vec(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.to_memory()?.to_vec())
+    }
 }
 
 impl ToMemory for Memory {
@@ -17,6 +23,10 @@ impl ToMemory for Memory {
             length: self.len() as u64,
         }))
     }
+
+    fn to_bytes_vec(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.to_vec())
+    }
 }
 
 impl<'a> ToMemory for &'a Memory {
@@ -26,6 +36,10 @@ impl<'a> ToMemory for &'a Memory {
             length: self.len() as u64,
         }))
     }
+
+    fn to_bytes_vec(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.to_vec())
+    }
 }
 
 #[cfg(feature = "http")]
@@ -39,13 +53,8 @@ impl<'a, T: ToBytes<'a>> ToMemory for T {
     fn to_memory(&self) -> Result<Memory, Error> {
         Memory::from_bytes(self.to_bytes()?)
     }
-}
 
-impl ToMemory for Base64 {
-    fn to_memory(&self) -> Result<Memory, Error> {
-        base64::engine::general_purpose::STANDARD
-            .encode(&self.0)
-            .as_str()
-            .to_memory()
+    fn to_bytes_vec(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.to_bytes()?.as_ref().to_vec())
     }
 }
@@ -1,20 +1,115 @@
 use crate::*;
 
-pub fn get_memory(key: impl AsRef<str>) -> Result<Option<Memory>, Error> {
-    let mem = Memory::from_bytes(key.as_ref().as_bytes())?;
+mod cache;
+mod collections;
+mod large;
+mod namespace;
+mod quota;
+mod store;
+mod transaction;
+mod versioned;
+
+pub use cache::{cache_enabled, disable_cache, enable_cache, flush};
+pub use collections::{VarMap, VarSet, VarVec};
+pub use large::{get_large, remove_large, set_large, set_large_chunked, LARGE_CHUNK_SIZE};
+pub use namespace::{Namespace, TypedVar};
+pub use quota::{usage, VarError};
+#[cfg(feature = "encryption")]
+pub use store::{key_from_config, KeySource};
+pub use store::{Compression, Store, StoreBuilder};
+#[doc(hidden)]
+pub use transaction::{begin_transaction, commit_transaction, rollback_transaction};
+pub use transaction::{transaction, Transaction};
+pub use versioned::VersionedVar;
 
-    let offset = unsafe { extism::var_get(mem.offset()) };
+pub(crate) fn get_host(key: &str) -> Result<Option<Memory>, Error> {
+    let key_mem = Memory::from_bytes(key.as_bytes())?;
+    let memory = get_host_memory(&key_mem);
+    key_mem.free();
+    quota::track(key, memory.as_ref().map(|m| m.len()));
+    Ok(memory)
+}
+
+fn get_host_memory(key: &Memory) -> Option<Memory> {
+    let offset = unsafe { extism::var_get(key.offset()) };
     if offset == 0 {
-        return Ok(None);
+        return None;
     }
     let length = unsafe { extism::length(offset) };
 
     if length == 0 {
-        return Ok(None);
+        return None;
+    }
+
+    Some(Memory(MemoryHandle { offset, length }))
+}
+
+/// Sets a variable on the host, then reads it back to make sure the host stored it
+pub(crate) fn set_host(key: &str, val: &Memory) -> Result<(), Error> {
+    let key_mem = Memory::from_bytes(key.as_bytes())?;
+    unsafe { extism::var_set(key_mem.offset(), val.offset()) }
+
+    let size = val.len();
+    let stored = size == 0
+        || get_host_memory(&key_mem).is_some_and(|mem| {
+            let stored = mem.len() == size && mem.to_vec() == val.to_vec();
+            mem.free();
+            stored
+        });
+    key_mem.free();
+
+    if !stored {
+        return Err(VarError::QuotaExceeded {
+            key: key.to_string(),
+            size,
+        }
+        .into());
     }
+    quota::track(key, Some(size));
+    Ok(())
+}
 
-    let memory = MemoryHandle { offset, length };
-    Ok(Some(Memory(memory)))
+pub(crate) fn remove_host(key: &str) -> Result<(), Error> {
+    let key_mem = Memory::from_bytes(key.as_bytes())?;
+    unsafe { extism::var_set(key_mem.offset(), 0) };
+    key_mem.free();
+    quota::track(key, None);
+    Ok(())
+}
+
+pub(crate) fn get_bytes(key: &str) -> Result<Option<Vec<u8>>, Error> {
+    if let Some(value) = transaction::lookup(key) {
+        return Ok(value);
+    }
+    if let Some(value) = cache::lookup(key) {
+        return Ok(value);
+    }
+    let value = get_host(key)?.map(|mem| {
+        let data = mem.to_vec();
+        mem.free();
+        data
+    });
+    cache::fill(key, value.as_deref());
+    Ok(value)
+}
+
+/// Returns true when a variable is set, without copying its value out of kernel memory
+pub(crate) fn exists(key: &str) -> Result<bool, Error> {
+    if let Some(value) = transaction::lookup(key) {
+        return Ok(value.is_some());
+    }
+    if let Some(value) = cache::lookup(key) {
+        return Ok(value.is_some());
+    }
+    Ok(get_host(key)?.map(Memory::free).is_some())
+}
+
+pub fn get_memory(key: impl AsRef<str>) -> Result<Option<Memory>, Error> {
+    let key = key.as_ref();
+    if cache_enabled() || transaction::active() {
+        return get_bytes(key)?.map(Memory::from_bytes).transpose();
+    }
+    get_host(key)
 }
 
 /// Gets a variable in the plug-in. This variable lives as long as the
@@ -32,7 +127,7 @@ pub fn get_memory(key: impl AsRef<str>) -> Result<Option<Memory>, Error> {
 /// let my_var = var::get("my_var")?.unwrap_or(0u32);
 /// ```
 pub fn get<T: FromBytesOwned>(key: impl AsRef<str>) -> Result<Option<T>, Error> {
-    match get_memory(key)?.map(|x| x.to_vec()) {
+    match get_bytes(key.as_ref())? {
         Some(v) => Ok(Some(T::from_bytes(&v)?)),
         None => Ok(None),
     }
@@ -41,6 +136,10 @@ pub fn get<T: FromBytesOwned>(key: impl AsRef<str>) -> Result<Option<T>, Error>
 /// Set a variable in the plug-in. This variable lives as long as the
 /// plug-in is loaded. The value must have a [ToMemory] implementation.
 ///
+/// The variable is read back after it's written, and [VarError::QuotaExceeded] is returned
+/// when the host didn't store it. When the write is staged by the variable cache or a
+/// transaction, the error is returned by [flush] or when the transaction is committed.
+///
 /// # Arguments
 ///
 /// * `key` - A unique string key to identify the variable
@@ -53,10 +152,117 @@ pub fn get<T: FromBytesOwned>(key: impl AsRef<str>) -> Result<Option<T>, Error>
 /// var::set("my_str_var", "Hello, World!")?;
 /// ```
 pub fn set(key: impl AsRef<str>, val: impl ToMemory) -> Result<(), Error> {
-    let val = val.to_memory()?;
-    let key = Memory::from_bytes(key.as_ref().as_bytes())?;
-    unsafe { extism::var_set(key.offset(), val.offset()) }
-    Ok(())
+    set_bytes(key.as_ref(), val.to_bytes_vec()?)
+}
+
+/// Sets a variable, kernel memory is only allocated when the write is passed to the host, and
+/// it's freed once the host has stored the value
+pub(crate) fn set_bytes(key: &str, data: Vec<u8>) -> Result<(), Error> {
+    if transaction::active() {
+        transaction::write(key, Some(data));
+        return Ok(());
+    }
+    if cache_enabled() {
+        cache::write(key, Some(data));
+        return Ok(());
+    }
+    let mem = Memory::from_bytes(&data)?;
+    let res = set_host(key, &mem);
+    mem.free();
+    res
+}
+
+/// Updates a variable using the previous value, and returns the new value. `f` is passed
+/// `None` when the variable isn't set.
+///
+/// Plug-in calls don't run concurrently, so no other call can modify the variable between the
+/// read and the write.
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+/// * `f` - Computes the new value from the previous value
+///
+/// # Examples
+///
+/// ```
+/// let names = var::update("names", |old: Option<Json<Vec<String>>>| {
+///     let Json(mut names) = old.unwrap_or_default();
+///     names.push(name);
+///     Json(names)
+/// })?;
+/// ```
+pub fn update<T: FromBytesOwned + ToMemory>(
+    key: impl AsRef<str>,
+    f: impl FnOnce(Option<T>) -> T,
+) -> Result<T, Error> {
+    let key = key.as_ref();
+    let new = f(get(key)?);
+    set_bytes(key, new.to_bytes_vec()?)?;
+    Ok(new)
+}
+
+/// Gets a variable, or sets it to the result of `f` if it isn't set yet
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+/// * `f` - Computes the value when the variable isn't set
+///
+/// # Examples
+///
+/// ```
+/// let id: String = var::get_or_insert_with("instance_id", || generate_id())?;
+/// ```
+pub fn get_or_insert_with<T: FromBytesOwned + ToMemory>(
+    key: impl AsRef<str>,
+    f: impl FnOnce() -> T,
+) -> Result<T, Error> {
+    let key = key.as_ref();
+    if let Some(x) = get(key)? {
+        return Ok(x);
+    }
+    let x = f();
+    set_bytes(key, x.to_bytes_vec()?)?;
+    Ok(x)
+}
+
+/// Adds `by` to a counter variable and returns the new value. A variable that isn't set
+/// is treated as 0.
+///
+/// Counters are stored as 8 byte little-endian `i64` values, the same encoding used by
+/// `var::set(key, n as i64)`, so hosts can read them directly. An error is returned if the
+/// variable holds a value with a different length, or if the counter would overflow.
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+/// * `by` - The amount to add, which may be negative
+///
+/// # Examples
+///
+/// ```
+/// let count = var::increment("count", 1)?;
+/// ```
+pub fn increment(key: impl AsRef<str>, by: i64) -> Result<i64, Error> {
+    let key = key.as_ref();
+    let old = match get_bytes(key)? {
+        Some(data) => {
+            let Ok(data) = <[u8; 8]>::try_from(data.as_slice()) else {
+                anyhow::bail!(
+                    "Variable {key:?} is not a counter, expected 8 bytes but found {}",
+                    data.len()
+                );
+            };
+            i64::from_le_bytes(data)
+        }
+        None => 0,
+    };
+    let Some(new) = old.checked_add(by) else {
+        anyhow::bail!("Counter {key:?} overflowed");
+    };
+    set(key, new)?;
+    Ok(new)
 }
 
 /// Removes a variable from the plug-in. This variable normally lives as long as the
@@ -72,7 +278,14 @@ pub fn set(key: impl AsRef<str>, val: impl ToMemory) -> Result<(), Error> {
 /// var::remove("my_var")?;
 /// ```
 pub fn remove(key: impl AsRef<str>) -> Result<(), Error> {
-    let key = Memory::from_bytes(key.as_ref().as_bytes())?;
-    unsafe { extism::var_set(key.offset(), 0) };
-    Ok(())
+    let key = key.as_ref();
+    if transaction::active() {
+        transaction::write(key, None);
+        return Ok(());
+    }
+    if cache_enabled() {
+        cache::write(key, None);
+        return Ok(());
+    }
+    remove_host(key)
 }
@@ -0,0 +1,112 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::*;
+
+struct Entry {
+    value: Option<Vec<u8>>,
+    dirty: bool,
+}
+
+thread_local! {
+    static CACHE: RefCell<Option<HashMap<String, Entry>>> = const { RefCell::new(None) };
+}
+
+/// Returns the cached value for `key`, the outer `Option` is `None` when the value isn't cached
+pub(crate) fn lookup(key: &str) -> Option<Option<Vec<u8>>> {
+    CACHE.with_borrow(|cache| Some(cache.as_ref()?.get(key)?.value.clone()))
+}
+
+/// Caches a value read from the host
+pub(crate) fn fill(key: &str, value: Option<&[u8]>) {
+    CACHE.with_borrow_mut(|cache| {
+        if let Some(cache) = cache {
+            cache.insert(
+                key.to_string(),
+                Entry {
+                    value: value.map(|v| v.to_vec()),
+                    dirty: false,
+                },
+            );
+        }
+    })
+}
+
+/// Stages a write, only valid while the cache is enabled
+pub(crate) fn write(key: &str, value: Option<Vec<u8>>) {
+    CACHE.with_borrow_mut(|cache| {
+        if let Some(cache) = cache {
+            cache.insert(key.to_string(), Entry { value, dirty: true });
+        }
+    })
+}
+
+/// Enables the write-back variable cache
+///
+/// While the cache is enabled, variables are only read from the host the first time they're
+/// accessed, and writes are kept in memory until [flush] is called. `#[plugin_fn(var_cache)]`
+/// enables the cache for the duration of a call and flushes it when the call returns.
+pub fn enable_cache() {
+    CACHE.with_borrow_mut(|cache| {
+        if cache.is_none() {
+            *cache = Some(HashMap::new());
+        }
+    })
+}
+
+/// Flushes and disables the variable cache
+pub fn disable_cache() -> Result<(), Error> {
+    let res = flush();
+    CACHE.set(None);
+    res
+}
+
+/// Returns true when the variable cache is enabled
+pub fn cache_enabled() -> bool {
+    CACHE.with_borrow(|cache| cache.is_some())
+}
+
+/// Writes every modified variable in the cache to the host
+///
+/// If the host rejects a write, the writes that were already applied are reverted to their
+/// previous values, see [var::transaction], and the variables stay modified in the cache.
+pub fn flush() -> Result<(), Error> {
+    let dirty: Vec<(String, Option<Vec<u8>>)> = CACHE.with_borrow(|cache| {
+        let Some(cache) = cache else {
+            return vec![];
+        };
+        cache
+            .iter()
+            .filter(|(_, entry)| entry.dirty)
+            .map(|(key, entry)| (key.clone(), entry.value.clone()))
+            .collect()
+    });
+    let keys: Vec<String> = dirty.iter().map(|(key, _)| key.clone()).collect();
+
+    let get = |key: &str| {
+        Ok(var::get_host(key)?.map(|mem| {
+            let data = mem.to_vec();
+            mem.free();
+            data
+        }))
+    };
+    let set = |key: &str, value: Option<Vec<u8>>| match value {
+        Some(value) => {
+            let mem = Memory::from_bytes(value)?;
+            let res = var::set_host(key, &mem);
+            mem.free();
+            res
+        }
+        None => var::remove_host(key),
+    };
+    var::transaction::apply_writes(dirty, get, set)?;
+
+    CACHE.with_borrow_mut(|cache| {
+        for key in keys {
+            if let Some(entry) = cache.as_mut().and_then(|c| c.get_mut(&key)) {
+                entry.dirty = false;
+            }
+        }
+    });
+    Ok(())
+}
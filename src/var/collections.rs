@@ -0,0 +1,362 @@
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::*;
+
+fn entry_key<K: Serialize>(name: &str, key: &K) -> Result<String, Error> {
+    Ok(format!("{name}/{}", serde_json::to_string(key)?))
+}
+
+/// A map stored across variables, with one variable per entry
+///
+/// Each entry is stored in `<name>/<key>`, where `key` is JSON encoded, and the keys are
+/// tracked in an index variable, `<name>#keys`, which holds a JSON array. Values are only
+/// loaded when they're accessed, and updating an existing entry only writes that entry's
+/// variable. Like [var::get], a value that encodes to zero bytes would be treated as missing,
+/// so [VarMap::insert] returns an error for it.
+///
+/// # Examples
+///
+/// ```
+/// let users = var::VarMap::<u64, Json<User>>::new("users")?;
+/// users.insert(&1, &Json(user))?;
+/// for entry in users.iter()? {
+///     let (id, Json(user)) = entry?;
+/// }
+/// ```
+pub struct VarMap<K, V> {
+    name: String,
+    _t: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> std::fmt::Debug for VarMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("VarMap").field(&self.name).finish()
+    }
+}
+
+impl<K, V> VarMap<K, V> {
+    /// Create a map, returns an error if `name` contains `/` or `#`, see [var::Namespace]
+    pub fn new(name: impl Into<String>) -> Result<Self, Error> {
+        let name = name.into();
+        var::namespace::check_name("map", &name)?;
+        Ok(VarMap {
+            name,
+            _t: PhantomData,
+        })
+    }
+
+    /// The name of the map
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn index_key(&self) -> String {
+        format!("{}#keys", self.name)
+    }
+}
+
+impl<K: Serialize + DeserializeOwned + PartialEq, V> VarMap<K, V> {
+    /// List the keys in the map
+    pub fn keys(&self) -> Result<Vec<K>, Error> {
+        let keys: Option<Json<Vec<K>>> = var::get(self.index_key())?;
+        Ok(keys.map(|Json(k)| k).unwrap_or_default())
+    }
+
+    fn set_keys(&self, keys: &[K]) -> Result<(), Error> {
+        if keys.is_empty() {
+            var::remove(self.index_key())
+        } else {
+            var::set(self.index_key(), Json(keys))
+        }
+    }
+
+    /// The number of entries in the map
+    pub fn len(&self) -> Result<usize, Error> {
+        Ok(self.keys()?.len())
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Returns true when the map contains `key`, without loading the index
+    pub fn contains_key(&self, key: &K) -> Result<bool, Error> {
+        var::exists(&entry_key(&self.name, key)?)
+    }
+
+    /// Removes an entry, returns true if it was present
+    pub fn remove(&self, key: &K) -> Result<bool, Error> {
+        let entry = entry_key(&self.name, key)?;
+        if !var::exists(&entry)? {
+            return Ok(false);
+        }
+        var::remove(entry)?;
+
+        let mut keys = self.keys()?;
+        keys.retain(|k| k != key);
+        self.set_keys(&keys)?;
+        Ok(true)
+    }
+
+    /// Removes every entry
+    pub fn clear(&self) -> Result<(), Error> {
+        for key in self.keys()? {
+            var::remove(entry_key(&self.name, &key)?)?;
+        }
+        var::remove(self.index_key())
+    }
+}
+
+impl<K: Serialize + DeserializeOwned + PartialEq, V: FromBytesOwned> VarMap<K, V> {
+    /// Gets the value for `key`
+    pub fn get(&self, key: &K) -> Result<Option<V>, Error> {
+        var::get(entry_key(&self.name, key)?)
+    }
+
+    /// Iterate over the entries in the map, each value is loaded as the iterator advances
+    pub fn iter(&self) -> Result<impl Iterator<Item = Result<(K, V), Error>> + '_, Error> {
+        Ok(self
+            .keys()?
+            .into_iter()
+            .filter_map(move |k| match self.get(&k) {
+                Ok(Some(v)) => Some(Ok((k, v))),
+                Ok(None) => None,
+                Err(e) => Some(Err(e)),
+            }))
+    }
+}
+
+impl<K: Serialize + DeserializeOwned + PartialEq + Clone, V: ToMemory> VarMap<K, V> {
+    /// Inserts or replaces the value for `key`, the index is only written when `key` is new.
+    /// Returns an error if the value encodes to zero bytes.
+    pub fn insert(&self, key: &K, val: &V) -> Result<(), Error> {
+        let entry = entry_key(&self.name, key)?;
+        let data = val.to_bytes_vec()?;
+        if data.is_empty() {
+            anyhow::bail!(
+                "Unable to insert into map {:?}, the value is empty",
+                self.name
+            );
+        }
+        let is_new = !var::exists(&entry)?;
+        var::set(entry, data)?;
+
+        if is_new {
+            let mut keys = self.keys()?;
+            if !keys.contains(key) {
+                keys.push(key.clone());
+                self.set_keys(&keys)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A list stored across variables, with one variable per element
+///
+/// Element `i` is stored in `<name>/<i>`, and the length is stored in `<name>#len` using the
+/// counter encoding from [var::increment]. Elements are only loaded when they're accessed, and
+/// `push`, `pop` and `set` only write the variables they change.
+///
+/// # Examples
+///
+/// ```
+/// let events = var::VarVec::<String>::new("events")?;
+/// events.push(&"started".to_string())?;
+/// let first = events.get(0)?;
+/// ```
+pub struct VarVec<T> {
+    name: String,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<T> std::fmt::Debug for VarVec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("VarVec").field(&self.name).finish()
+    }
+}
+
+impl<T> VarVec<T> {
+    /// Create a list, returns an error if `name` contains `/` or `#`, see [var::Namespace]
+    pub fn new(name: impl Into<String>) -> Result<Self, Error> {
+        let name = name.into();
+        var::namespace::check_name("list", &name)?;
+        Ok(VarVec {
+            name,
+            _t: PhantomData,
+        })
+    }
+
+    /// The name of the list
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn len_key(&self) -> String {
+        format!("{}#len", self.name)
+    }
+
+    fn element_key(&self, index: usize) -> String {
+        format!("{}/{index}", self.name)
+    }
+
+    /// The number of elements in the list
+    pub fn len(&self) -> Result<usize, Error> {
+        let len: Option<i64> = var::get(self.len_key())?;
+        Ok(len.unwrap_or(0) as usize)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        Ok(self.len()? == 0)
+    }
+
+    fn set_len(&self, len: usize) -> Result<(), Error> {
+        if len == 0 {
+            var::remove(self.len_key())
+        } else {
+            var::set(self.len_key(), len as i64)
+        }
+    }
+
+    /// Shortens the list to `len` elements, removing the rest
+    pub fn truncate(&self, len: usize) -> Result<(), Error> {
+        let old = self.len()?;
+        if len >= old {
+            return Ok(());
+        }
+        for i in len..old {
+            var::remove(self.element_key(i))?;
+        }
+        self.set_len(len)
+    }
+
+    /// Removes every element
+    pub fn clear(&self) -> Result<(), Error> {
+        self.truncate(0)
+    }
+}
+
+impl<T: FromBytesOwned> VarVec<T> {
+    /// Gets the element at `index`
+    pub fn get(&self, index: usize) -> Result<Option<T>, Error> {
+        if index >= self.len()? {
+            return Ok(None);
+        }
+        var::get(self.element_key(index))
+    }
+
+    /// Removes the last element and returns it
+    pub fn pop(&self) -> Result<Option<T>, Error> {
+        let len = self.len()?;
+        if len == 0 {
+            return Ok(None);
+        }
+        let key = self.element_key(len - 1);
+        let x = var::get(&key)?;
+        var::remove(key)?;
+        self.set_len(len - 1)?;
+        Ok(x)
+    }
+
+    /// Iterate over the elements, each element is loaded as the iterator advances
+    pub fn iter(&self) -> Result<impl Iterator<Item = Result<T, Error>> + '_, Error> {
+        Ok((0..self.len()?).filter_map(move |i| var::get(self.element_key(i)).transpose()))
+    }
+}
+
+impl<T: ToMemory> VarVec<T> {
+    /// Appends an element
+    pub fn push(&self, val: &T) -> Result<(), Error> {
+        let len = self.len()?;
+        var::set(self.element_key(len), val.to_bytes_vec()?)?;
+        self.set_len(len + 1)
+    }
+
+    /// Replaces the element at `index`, returns an error if `index` is out of bounds
+    pub fn set(&self, index: usize, val: &T) -> Result<(), Error> {
+        let len = self.len()?;
+        if index >= len {
+            anyhow::bail!(
+                "Index {index} is out of bounds for {:?} with length {len}",
+                self.name
+            );
+        }
+        var::set(self.element_key(index), val.to_bytes_vec()?)
+    }
+}
+
+/// A set stored across variables, with one variable per element
+///
+/// Elements are stored like the keys of a [VarMap], so `contains` only reads a single
+/// variable.
+///
+/// # Examples
+///
+/// ```
+/// let seen = var::VarSet::<String>::new("seen")?;
+/// if !seen.insert(&id)? {
+///     return Ok(());
+/// }
+/// ```
+pub struct VarSet<T>(VarMap<T, bool>);
+
+impl<T> std::fmt::Debug for VarSet<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("VarSet").field(&self.0.name).finish()
+    }
+}
+
+impl<T> VarSet<T> {
+    /// Create a set, returns an error if `name` contains `/` or `#`, see [var::Namespace]
+    pub fn new(name: impl Into<String>) -> Result<Self, Error> {
+        Ok(VarSet(VarMap::new(name)?))
+    }
+
+    /// The name of the set
+    pub fn name(&self) -> &str {
+        self.0.name()
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + PartialEq + Clone> VarSet<T> {
+    /// Adds an element, returns true if it wasn't already present
+    pub fn insert(&self, val: &T) -> Result<bool, Error> {
+        if self.0.contains_key(val)? {
+            return Ok(false);
+        }
+        self.0.insert(val, &true)?;
+        Ok(true)
+    }
+
+    /// Returns true when the set contains `val`
+    pub fn contains(&self, val: &T) -> Result<bool, Error> {
+        self.0.contains_key(val)
+    }
+
+    /// Removes an element, returns true if it was present
+    pub fn remove(&self, val: &T) -> Result<bool, Error> {
+        self.0.remove(val)
+    }
+
+    /// List the elements in the set
+    pub fn values(&self) -> Result<Vec<T>, Error> {
+        self.0.keys()
+    }
+
+    /// The number of elements in the set
+    pub fn len(&self) -> Result<usize, Error> {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        self.0.is_empty()
+    }
+
+    /// Removes every element
+    pub fn clear(&self) -> Result<(), Error> {
+        self.0.clear()
+    }
+}
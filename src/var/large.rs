@@ -0,0 +1,160 @@
+use crate::*;
+
+/// The chunk size used by [set_large]
+pub const LARGE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Stored in the variable named by the key, describes the chunks that make up a large value
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Manifest {
+    generation: u64,
+    chunks: usize,
+    length: usize,
+    crc32: u32,
+}
+
+impl Manifest {
+    fn chunk_key(&self, key: &str, index: usize) -> String {
+        format!("{key}#{}#{index}", self.generation)
+    }
+
+    fn remove_chunks(&self, key: &str, count: usize) -> Result<(), Error> {
+        for i in 0..count {
+            var::remove(self.chunk_key(key, i))?;
+        }
+        Ok(())
+    }
+}
+
+/// CRC-32 (IEEE), the same checksum used by zlib and gzip
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB88320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn manifest(key: &str) -> Result<Option<Manifest>, Error> {
+    let manifest: Option<Json<Manifest>> = var::get(key)?;
+    Ok(manifest.map(|Json(m)| m))
+}
+
+/// Set a variable that may be larger than the host allows for a single variable, using
+/// chunks of [LARGE_CHUNK_SIZE] bytes
+///
+/// The value is split into chunks stored in `<key>#<generation>#0`, `<key>#<generation>#1`,
+/// etc. and `key` holds a JSON manifest with the generation, the number of chunks, the total
+/// length and a CRC-32 checksum of the value. Every write uses a new generation, and the
+/// chunks of the previous value are only removed once the manifest points at the new chunks,
+/// so a write that fails, i.e. with [var::VarError::QuotaExceeded], leaves the previous value
+/// intact. This means the host needs room for both values while a value is replaced.
+/// Use [get_large] to read the value and [remove_large] to remove it.
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+/// * `val` - The value to set. Must have a [ToMemory] implementation
+///
+/// # Examples
+///
+/// ```
+/// var::set_large("embeddings", Json(&embeddings))?;
+/// let embeddings: Option<Json<Vec<Vec<f32>>>> = var::get_large("embeddings")?;
+/// ```
+pub fn set_large(key: impl AsRef<str>, val: impl ToMemory) -> Result<(), Error> {
+    set_large_chunked(key, val, LARGE_CHUNK_SIZE)
+}
+
+/// Like [set_large], with a custom chunk size
+pub fn set_large_chunked(
+    key: impl AsRef<str>,
+    val: impl ToMemory,
+    chunk_size: usize,
+) -> Result<(), Error> {
+    let key = key.as_ref();
+    if chunk_size == 0 {
+        anyhow::bail!("Chunk size must be greater than 0");
+    }
+    let data = val.to_bytes_vec()?;
+    let old = manifest(key)?;
+
+    let chunks = data.chunks(chunk_size);
+    let manifest = Manifest {
+        generation: old.as_ref().map_or(0, |m| m.generation.wrapping_add(1)),
+        chunks: chunks.len(),
+        length: data.len(),
+        crc32: crc32(&data),
+    };
+
+    // The new chunks are written next to the previous value, which stays readable until the
+    // manifest is replaced. If any write fails, the chunks written so far are removed again,
+    // a chunk that can't be removed isn't referenced by any manifest so the original error
+    // is returned either way.
+    for (i, chunk) in chunks.enumerate() {
+        if let Err(e) = var::set(manifest.chunk_key(key, i), chunk) {
+            manifest.remove_chunks(key, i).ok();
+            return Err(e);
+        }
+    }
+    if let Err(e) = var::set(key, Json(&manifest)) {
+        manifest.remove_chunks(key, manifest.chunks).ok();
+        return Err(e);
+    }
+
+    match old {
+        Some(old) => old.remove_chunks(key, old.chunks),
+        None => Ok(()),
+    }
+}
+
+/// Gets a variable set using [set_large]
+///
+/// An error is returned when a chunk is missing, or the reassembled value doesn't match the
+/// length and checksum in the manifest.
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+pub fn get_large<T: FromBytesOwned>(key: impl AsRef<str>) -> Result<Option<T>, Error> {
+    let key = key.as_ref();
+    let Some(manifest) = manifest(key)? else {
+        return Ok(None);
+    };
+
+    let mut data = Vec::with_capacity(manifest.length);
+    for i in 0..manifest.chunks {
+        let Some(chunk) = var::get_bytes(&manifest.chunk_key(key, i))? else {
+            anyhow::bail!("Variable {key:?} is corrupt, chunk {i} is missing");
+        };
+        data.extend_from_slice(&chunk);
+    }
+
+    if data.len() != manifest.length {
+        anyhow::bail!(
+            "Variable {key:?} is corrupt, expected {} bytes but found {}",
+            manifest.length,
+            data.len()
+        );
+    }
+    if crc32(&data) != manifest.crc32 {
+        anyhow::bail!("Variable {key:?} is corrupt, checksum mismatch");
+    }
+    Ok(Some(T::from_bytes_owned(&data)?))
+}
+
+/// Removes a variable set using [set_large], including all of its chunks
+///
+/// # Arguments
+///
+/// * `key` - A unique string key to identify the variable
+pub fn remove_large(key: impl AsRef<str>) -> Result<(), Error> {
+    let key = key.as_ref();
+    let Some(manifest) = manifest(key)? else {
+        return Ok(());
+    };
+    manifest.remove_chunks(key, manifest.chunks)?;
+    var::remove(key)
+}
@@ -0,0 +1,203 @@
+use std::marker::PhantomData;
+
+use crate::*;
+
+/// A set of variables that share a key prefix
+///
+/// Keys are stored as `<name>/<key>`, so different libraries or modules can use the same key
+/// names without colliding. Since the kernel has no way to list variables, the keys in a
+/// namespace are tracked in an index variable, `<name>#keys`, which holds a JSON array of
+/// key names.
+///
+/// Namespace names can't contain `/` or `#`, so the prefix of every key is unambiguous: keys
+/// may contain any character, but `Namespace::new("a")` with the key `b/c` and
+/// `Namespace::new("a/b")` with the key `c` would otherwise both be stored in `a/b/c`.
+///
+/// # Examples
+///
+/// ```
+/// let cache = var::Namespace::new("cache")?;
+/// cache.set("greeting", "Hello")?;
+/// let greeting: Option<String> = cache.get("greeting")?;
+/// cache.clear()?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Namespace {
+    name: String,
+}
+
+/// Returns an error if `name` can't be used as the prefix of a [Namespace], [VarMap] or [VarVec]
+pub(crate) fn check_name(kind: &str, name: &str) -> Result<(), Error> {
+    if name.contains(['/', '#']) {
+        anyhow::bail!("Invalid {kind} name {name:?}, names can't contain '/' or '#'");
+    }
+    Ok(())
+}
+
+impl Namespace {
+    /// Create a namespace, returns an error if `name` contains `/` or `#`
+    pub fn new(name: impl Into<String>) -> Result<Self, Error> {
+        let name = name.into();
+        check_name("namespace", &name)?;
+        Ok(Namespace { name })
+    }
+
+    /// The name of the namespace
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Bind the namespace to a single value type
+    pub fn typed<T>(self) -> TypedVar<T> {
+        TypedVar {
+            namespace: self,
+            _t: PhantomData,
+        }
+    }
+
+    /// Returns the full variable key used to store `key`
+    pub fn key(&self, key: impl AsRef<str>) -> String {
+        format!("{}/{}", self.name, key.as_ref())
+    }
+
+    fn index_key(&self) -> String {
+        format!("{}#keys", self.name)
+    }
+
+    /// List the keys stored in the namespace, without the prefix
+    pub fn keys(&self) -> Result<Vec<String>, Error> {
+        let keys: Option<Json<Vec<String>>> = var::get(self.index_key())?;
+        Ok(keys.map(|Json(k)| k).unwrap_or_default())
+    }
+
+    fn set_keys(&self, keys: Vec<String>) -> Result<(), Error> {
+        if keys.is_empty() {
+            var::remove(self.index_key())
+        } else {
+            var::set(self.index_key(), Json(keys))
+        }
+    }
+
+    /// Returns true when `key` is stored in the namespace
+    pub fn contains(&self, key: impl AsRef<str>) -> Result<bool, Error> {
+        let key = key.as_ref();
+        Ok(self.keys()?.iter().any(|k| k == key))
+    }
+
+    /// Gets a variable from the namespace, see [var::get]
+    pub fn get<T: FromBytesOwned>(&self, key: impl AsRef<str>) -> Result<Option<T>, Error> {
+        var::get(self.key(key))
+    }
+
+    /// Gets the raw memory for a variable in the namespace, see [var::get_memory]
+    pub fn get_memory(&self, key: impl AsRef<str>) -> Result<Option<Memory>, Error> {
+        var::get_memory(self.key(key))
+    }
+
+    /// Sets a variable in the namespace, see [var::set]
+    pub fn set(&self, key: impl AsRef<str>, val: impl ToMemory) -> Result<(), Error> {
+        let key = key.as_ref();
+        var::set(self.key(key), val)?;
+
+        let mut keys = self.keys()?;
+        if !keys.iter().any(|k| k == key) {
+            keys.push(key.to_string());
+            self.set_keys(keys)?;
+        }
+        Ok(())
+    }
+
+    /// Removes a variable from the namespace, see [var::remove]
+    pub fn remove(&self, key: impl AsRef<str>) -> Result<(), Error> {
+        let key = key.as_ref();
+        var::remove(self.key(key))?;
+
+        let mut keys = self.keys()?;
+        let len = keys.len();
+        keys.retain(|k| k != key);
+        if keys.len() != len {
+            self.set_keys(keys)?;
+        }
+        Ok(())
+    }
+
+    /// Removes every variable in the namespace
+    pub fn clear(&self) -> Result<(), Error> {
+        for key in self.keys()? {
+            var::remove(self.key(key))?;
+        }
+        var::remove(self.index_key())
+    }
+}
+
+/// A [Namespace] where every value has the type `T`
+///
+/// # Examples
+///
+/// ```
+/// let sessions = var::TypedVar::<Json<Session>>::new("sessions")?;
+/// sessions.set("abc", &Json(session))?;
+/// let session = sessions.get("abc")?;
+/// ```
+pub struct TypedVar<T> {
+    namespace: Namespace,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<T> std::fmt::Debug for TypedVar<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TypedVar").field(&self.namespace).finish()
+    }
+}
+
+impl<T> Clone for TypedVar<T> {
+    fn clone(&self) -> Self {
+        self.namespace.clone().typed()
+    }
+}
+
+impl<T> TypedVar<T> {
+    /// Create a typed namespace, returns an error if `name` contains `/` or `#`
+    pub fn new(name: impl Into<String>) -> Result<Self, Error> {
+        Ok(Namespace::new(name)?.typed())
+    }
+
+    /// The underlying namespace
+    pub fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    /// List the keys stored in the namespace, without the prefix
+    pub fn keys(&self) -> Result<Vec<String>, Error> {
+        self.namespace.keys()
+    }
+
+    /// Returns true when `key` is stored in the namespace
+    pub fn contains(&self, key: impl AsRef<str>) -> Result<bool, Error> {
+        self.namespace.contains(key)
+    }
+
+    /// Removes a value
+    pub fn remove(&self, key: impl AsRef<str>) -> Result<(), Error> {
+        self.namespace.remove(key)
+    }
+
+    /// Removes every value
+    pub fn clear(&self) -> Result<(), Error> {
+        self.namespace.clear()
+    }
+}
+
+impl<T: FromBytesOwned> TypedVar<T> {
+    /// Gets a value
+    pub fn get(&self, key: impl AsRef<str>) -> Result<Option<T>, Error> {
+        self.namespace.get(key)
+    }
+}
+
+impl<T: ToMemory> TypedVar<T> {
+    /// Sets a value
+    pub fn set(&self, key: impl AsRef<str>, val: &T) -> Result<(), Error> {
+        self.namespace.set(key, val.to_bytes_vec()?)
+    }
+}
@@ -0,0 +1,48 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+thread_local! {
+    static SIZES: RefCell<HashMap<String, usize>> = RefCell::new(HashMap::new());
+}
+
+/// Errors returned by variable operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VarError {
+    /// The host didn't store a variable, usually because the plug-in's variable storage limit
+    /// was reached
+    QuotaExceeded { key: String, size: usize },
+}
+
+impl std::fmt::Display for VarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VarError::QuotaExceeded { key, size } => write!(
+                f,
+                "Unable to store variable {key:?} ({size} bytes), the variable quota may be exceeded"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VarError {}
+
+/// Records the size of a variable stored on the host
+pub(crate) fn track(key: &str, size: Option<usize>) {
+    SIZES.with_borrow_mut(|sizes| match size {
+        Some(size) => {
+            sizes.insert(key.to_string(), key.len() + size);
+        }
+        None => {
+            sizes.remove(key);
+        }
+    })
+}
+
+/// Estimates the number of bytes used by variables
+///
+/// The estimate is the size of every key and value this plug-in instance has read or written
+/// since it was loaded, so it can be used to evict entries before the host's limit is reached.
+/// Variables that haven't been accessed yet aren't included.
+pub fn usage() -> usize {
+    SIZES.with_borrow(|sizes| sizes.values().sum())
+}
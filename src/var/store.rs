@@ -0,0 +1,457 @@
+use crate::*;
+
+#[cfg(feature = "encryption")]
+use crate::encryption::{Cipher, EncryptionKey};
+
+/// Marks a value written by [Store]
+const MAGIC: &[u8] = b"\xffVS";
+
+/// The compression used by a [Store]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "gzip")]
+    Gzip,
+}
+
+impl Compression {
+    fn id(self) -> u8 {
+        match self {
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => 1,
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => 2,
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "zstd", feature = "gzip")), allow(unused_variables))]
+    fn compress(self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Zstd(data).to_bytes(),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Gzip(data).to_bytes(),
+        }
+    }
+}
+
+fn decompress(id: u8, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+    match id {
+        0 => Ok(data),
+        #[cfg(feature = "zstd")]
+        1 => Ok(Zstd::<Vec<u8>>::from_bytes_owned(&data)?.0),
+        #[cfg(feature = "gzip")]
+        2 => Ok(Gzip::<Vec<u8>>::from_bytes_owned(&data)?.0),
+        _ => anyhow::bail!("Unsupported compression id {id}, the feature may not be enabled"),
+    }
+}
+
+/// Where a [Store] loads its encryption key from
+#[cfg(feature = "encryption")]
+#[derive(Debug)]
+pub enum KeySource {
+    /// A base64 encoded key in the named config item
+    Config(String),
+    Key(EncryptionKey),
+}
+
+#[cfg(feature = "encryption")]
+impl KeySource {
+    fn load(self) -> Result<EncryptionKey, Error> {
+        match self {
+            KeySource::Config(name) => EncryptionKey::from_config(name),
+            KeySource::Key(key) => Ok(key),
+        }
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl From<EncryptionKey> for KeySource {
+    fn from(key: EncryptionKey) -> Self {
+        KeySource::Key(key)
+    }
+}
+
+/// Load a [Store] encryption key from a base64 encoded config item
+#[cfg(feature = "encryption")]
+pub fn key_from_config(name: impl Into<String>) -> KeySource {
+    KeySource::Config(name.into())
+}
+
+/// Builds a [Store]
+#[derive(Debug, Default)]
+pub struct StoreBuilder {
+    compression: Option<Compression>,
+    #[cfg(feature = "encryption")]
+    key: Option<KeySource>,
+    #[cfg(feature = "encryption")]
+    previous_keys: Vec<KeySource>,
+    #[cfg(feature = "encryption")]
+    cipher: Option<Cipher>,
+}
+
+impl StoreBuilder {
+    /// Compress values before they're stored
+    pub fn compress(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Encrypt values before they're stored
+    #[cfg(feature = "encryption")]
+    pub fn encrypt(mut self, key: impl Into<KeySource>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Add a previous key, values encrypted using it are re-encrypted with the current key
+    /// when they're read
+    #[cfg(feature = "encryption")]
+    pub fn previous_key(mut self, key: impl Into<KeySource>) -> Self {
+        self.previous_keys.push(key.into());
+        self
+    }
+
+    /// Set the cipher used to encrypt values, the default is AES-256-GCM
+    #[cfg(feature = "encryption")]
+    pub fn cipher(mut self, cipher: Cipher) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// Load the encryption keys and create the [Store]
+    pub fn build(self) -> Result<Store, Error> {
+        Ok(Store {
+            compression: self.compression,
+            #[cfg(feature = "encryption")]
+            key: self.key.map(KeySource::load).transpose()?,
+            #[cfg(feature = "encryption")]
+            previous_keys: self
+                .previous_keys
+                .into_iter()
+                .map(KeySource::load)
+                .collect::<Result<_, _>>()?,
+            #[cfg(feature = "encryption")]
+            cipher: self.cipher.unwrap_or(Cipher::Aes256Gcm),
+        })
+    }
+}
+
+/// Compressed and encrypted variables
+///
+/// A `Store` wraps [var::get] and [var::set] so values are compressed, then encrypted, before
+/// they're passed to the host. Stored values start with `0xff 'V' 'S'`, followed by a
+/// compression id byte and an encryption flag byte. Encrypted values then contain the 8 byte
+/// id of the key, see [EncryptionKey::id], followed by the encrypted payload. The header,
+/// including the key id, and the variable name are used as associated data, so changing
+/// either makes the value fail to decrypt. The key id lets a store with
+/// [StoreBuilder::previous_key] decrypt values written before a key was rotated.
+///
+/// # Examples
+///
+/// ```
+/// let store = var::Store::builder()
+///     .compress(var::Compression::Zstd)
+///     .encrypt(var::key_from_config("var_key"))
+///     .build()?;
+/// store.set("session", Json(&session))?;
+/// let session: Option<Json<Session>> = store.get("session")?;
+/// ```
+#[derive(Debug)]
+pub struct Store {
+    compression: Option<Compression>,
+    #[cfg(feature = "encryption")]
+    key: Option<EncryptionKey>,
+    #[cfg(feature = "encryption")]
+    previous_keys: Vec<EncryptionKey>,
+    #[cfg(feature = "encryption")]
+    cipher: Cipher,
+}
+
+impl Store {
+    pub fn builder() -> StoreBuilder {
+        StoreBuilder::default()
+    }
+
+    /// Gets a variable written by the store, see [var::get]
+    pub fn get<T: FromBytesOwned>(&self, key: impl AsRef<str>) -> Result<Option<T>, Error> {
+        let key = key.as_ref();
+        let Some(value) = var::get_bytes(key)? else {
+            return Ok(None);
+        };
+        let (data, previous_key) = self
+            .decode(key, value)
+            .map_err(|e| e.context(format!("Unable to read variable {key:?}")))?;
+
+        // Re-encrypt values written using a previous key
+        if previous_key {
+            self.set(key, data.as_slice())?;
+        }
+        Ok(Some(T::from_bytes_owned(&data)?))
+    }
+
+    /// Sets a variable, compressing and encrypting it first, see [var::set]
+    pub fn set(&self, key: impl AsRef<str>, val: impl ToMemory) -> Result<(), Error> {
+        let key = key.as_ref();
+        let data = val.to_bytes_vec()?;
+        var::set(key, self.encode(key, &data)?)
+    }
+
+    /// Removes a variable, see [var::remove]
+    pub fn remove(&self, key: impl AsRef<str>) -> Result<(), Error> {
+        var::remove(key)
+    }
+
+    fn encode(&self, key: &str, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let data = match self.compression {
+            Some(c) => c.compress(data)?,
+            None => data.to_vec(),
+        };
+
+        let mut value = MAGIC.to_vec();
+        value.push(self.compression.map_or(0, Compression::id));
+        if !self.seal(key, &mut value, &data)? {
+            value.push(0);
+            value.extend_from_slice(&data);
+        }
+        Ok(value)
+    }
+
+    /// Decodes a stored value, and returns true if it was encrypted using a previous key
+    fn decode(&self, key: &str, value: Vec<u8>) -> Result<(Vec<u8>, bool), Error> {
+        let Some(header) = value.strip_prefix(MAGIC) else {
+            anyhow::bail!("Value wasn't written by var::Store");
+        };
+        let [compression, encrypted, ..] = *header else {
+            anyhow::bail!("Value is truncated");
+        };
+        let (header, data) = value.split_at(MAGIC.len() + 2);
+
+        match encrypted {
+            0 => {
+                if self.encrypted() {
+                    anyhow::bail!("Value isn't encrypted");
+                }
+                Ok((decompress(compression, data.to_vec())?, false))
+            }
+            1 => self.open(key, header, compression, data),
+            _ => anyhow::bail!("Unsupported encryption flag {encrypted}"),
+        }
+    }
+
+    #[cfg(feature = "encryption")]
+    fn encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Appends the encryption flag, the key id and the encrypted payload to `value`, which
+    /// holds the header so far. Returns false when the store doesn't encrypt values.
+    #[cfg(feature = "encryption")]
+    fn seal(&self, key: &str, value: &mut Vec<u8>, data: &[u8]) -> Result<bool, Error> {
+        let Some(enc) = &self.key else {
+            return Ok(false);
+        };
+        value.push(1);
+        value.extend_from_slice(&enc.id());
+        let aad = [value.as_slice(), key.as_bytes()].concat();
+        value.extend(encryption::seal(self.cipher, enc, &aad, data)?);
+        Ok(true)
+    }
+
+    #[cfg(feature = "encryption")]
+    fn open(
+        &self,
+        key: &str,
+        header: &[u8],
+        compression: u8,
+        data: &[u8],
+    ) -> Result<(Vec<u8>, bool), Error> {
+        let Some(current) = &self.key else {
+            anyhow::bail!("Value is encrypted, but the store has no key");
+        };
+        if data.len() < 8 {
+            anyhow::bail!("Value is truncated");
+        }
+        let (id, sealed) = data.split_at(8);
+        let Some(enc) = std::iter::once(current)
+            .chain(&self.previous_keys)
+            .find(|k| k.id() == id)
+        else {
+            anyhow::bail!("Value is encrypted with an unknown key");
+        };
+        let aad = [header, id, key.as_bytes()].concat();
+        let data = decompress(compression, encryption::open(enc, &aad, sealed)?)?;
+        Ok((data, enc.id() != current.id()))
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn encrypted(&self) -> bool {
+        false
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn seal(&self, _key: &str, _value: &mut Vec<u8>, _data: &[u8]) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn open(
+        &self,
+        _key: &str,
+        _header: &[u8],
+        _compression: u8,
+        _data: &[u8],
+    ) -> Result<(Vec<u8>, bool), Error> {
+        anyhow::bail!("Value is encrypted, but the encryption feature is not enabled")
+    }
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::*;
+
+    fn store(compression: Option<Compression>, cipher: Cipher) -> Store {
+        Store {
+            compression,
+            key: Some(EncryptionKey::new(vec![1; 32]).unwrap()),
+            previous_keys: vec![],
+            cipher,
+        }
+    }
+
+    fn stores() -> Vec<Store> {
+        let mut compressions = vec![None];
+        #[cfg(feature = "zstd")]
+        compressions.push(Some(Compression::Zstd));
+        #[cfg(feature = "gzip")]
+        compressions.push(Some(Compression::Gzip));
+
+        let mut stores = vec![];
+        for compression in compressions {
+            for cipher in [Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305] {
+                stores.push(store(compression, cipher));
+            }
+        }
+        stores
+    }
+
+    const DATA: &[u8] = b"a value that is long enough to be compressed, compressed, compressed";
+
+    #[test]
+    fn round_trip() {
+        for store in stores() {
+            let value = store.encode("session", DATA).unwrap();
+            assert_eq!(
+                store.decode("session", value).unwrap(),
+                (DATA.to_vec(), false)
+            );
+        }
+
+        let mut plain = store(None, Cipher::Aes256Gcm);
+        plain.key = None;
+        let value = plain.encode("session", DATA).unwrap();
+        assert_eq!(
+            plain.decode("session", value).unwrap(),
+            (DATA.to_vec(), false)
+        );
+    }
+
+    #[test]
+    fn previous_key() {
+        let old = store(None, Cipher::Aes256Gcm);
+        let mut new = store(None, Cipher::Aes256Gcm);
+        new.key = Some(EncryptionKey::new(vec![2; 32]).unwrap());
+
+        let value = old.encode("session", DATA).unwrap();
+        assert!(new.decode("session", value.clone()).is_err());
+        new.previous_keys
+            .push(EncryptionKey::new(vec![1; 32]).unwrap());
+        assert_eq!(new.decode("session", value).unwrap(), (DATA.to_vec(), true));
+    }
+
+    #[test]
+    fn changed_header() {
+        // The magic, compression id, encryption flag and key id
+        let header_len = MAGIC.len() + 2 + 8;
+        for store in stores() {
+            let value = store.encode("session", DATA).unwrap();
+            for i in 0..header_len {
+                for bit in 0..8 {
+                    let mut changed = value.clone();
+                    changed[i] ^= 1 << bit;
+                    assert!(
+                        store.decode("session", changed).is_err(),
+                        "{store:?}, byte {i}, bit {bit}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn changed_compression_id() {
+        for store in stores() {
+            let value = store.encode("session", DATA).unwrap();
+            for id in 0..=2 {
+                let mut changed = value.clone();
+                if changed[MAGIC.len()] == id {
+                    continue;
+                }
+                changed[MAGIC.len()] = id;
+                assert!(
+                    store.decode("session", changed).is_err(),
+                    "{store:?}, id {id}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn changed_payload() {
+        for store in stores() {
+            let value = store.encode("session", DATA).unwrap();
+            for i in MAGIC.len() + 2 + 8..value.len() {
+                let mut changed = value.clone();
+                changed[i] ^= 1;
+                assert!(
+                    store.decode("session", changed).is_err(),
+                    "{store:?}, byte {i}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_variable() {
+        for store in stores() {
+            let value = store.encode("session", DATA).unwrap();
+            assert!(store.decode("other", value).is_err(), "{store:?}");
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        for store in stores() {
+            let value = store.encode("session", DATA).unwrap();
+            for len in 0..value.len() {
+                assert!(
+                    store.decode("session", value[..len].to_vec()).is_err(),
+                    "{store:?}, length {len}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn encryption_mismatch() {
+        let mut plain = store(None, Cipher::Aes256Gcm);
+        plain.key = None;
+        let encrypted = store(None, Cipher::Aes256Gcm);
+
+        let value = plain.encode("session", DATA).unwrap();
+        assert!(encrypted.decode("session", value).is_err());
+        let value = encrypted.encode("session", DATA).unwrap();
+        assert!(plain.decode("session", value).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::*;
+
+type Layer = HashMap<String, Option<Vec<u8>>>;
+
+thread_local! {
+    static LAYERS: RefCell<Vec<Layer>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns true while a transaction is active
+pub(crate) fn active() -> bool {
+    LAYERS.with_borrow(|layers| !layers.is_empty())
+}
+
+/// Returns the staged value for `key`, the outer `Option` is `None` when nothing is staged
+pub(crate) fn lookup(key: &str) -> Option<Option<Vec<u8>>> {
+    LAYERS.with_borrow(|layers| {
+        layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.get(key).cloned())
+    })
+}
+
+/// Stages a write, only valid while a transaction is active
+pub(crate) fn write(key: &str, value: Option<Vec<u8>>) {
+    LAYERS.with_borrow_mut(|layers| {
+        if let Some(layer) = layers.last_mut() {
+            layer.insert(key.to_string(), value);
+        }
+    })
+}
+
+#[doc(hidden)]
+pub fn begin_transaction() {
+    LAYERS.with_borrow_mut(|layers| layers.push(HashMap::new()))
+}
+
+#[doc(hidden)]
+pub fn commit_transaction() -> Result<(), Error> {
+    let Some(layer) = LAYERS.with_borrow_mut(|layers| layers.pop()) else {
+        return Ok(());
+    };
+
+    // Nested transactions are merged into the enclosing transaction
+    if active() {
+        LAYERS.with_borrow_mut(|layers| layers.last_mut().unwrap().extend(layer));
+        return Ok(());
+    }
+
+    apply_writes(layer, var::get_bytes, |key, value| match value {
+        Some(value) => var::set_bytes(key, value),
+        None => var::remove(key),
+    })
+}
+
+/// Applies `writes` using `set`, reading the previous value of each variable using `get`
+/// first. If a write fails, the writes that were already applied are reverted in reverse
+/// order, so every step returns to a state the host has already accepted, and the error is
+/// returned with a description of the first write that couldn't be reverted.
+pub(crate) fn apply_writes(
+    writes: impl IntoIterator<Item = (String, Option<Vec<u8>>)>,
+    get: impl Fn(&str) -> Result<Option<Vec<u8>>, Error>,
+    set: impl Fn(&str, Option<Vec<u8>>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    // Removals are applied first so they free space for the writes
+    let mut writes: Vec<_> = writes.into_iter().collect();
+    writes.sort_by_key(|(_, value)| value.is_some());
+
+    let mut applied: Vec<(String, Option<Vec<u8>>)> = Vec::with_capacity(writes.len());
+    for (key, value) in writes {
+        match get(&key).and_then(|previous| set(&key, value).map(|()| previous)) {
+            Ok(previous) => applied.push((key, previous)),
+            Err(e) => {
+                for (key, previous) in applied.into_iter().rev() {
+                    if let Err(restore) = set(&key, previous) {
+                        return Err(
+                            e.context(format!("Unable to restore variable {key:?}: {restore}"))
+                        );
+                    }
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[doc(hidden)]
+pub fn rollback_transaction() {
+    LAYERS.with_borrow_mut(|layers| layers.pop());
+}
+
+/// A handle to the variables in an active transaction, see [transaction]
+#[derive(Debug)]
+pub struct Transaction {
+    _private: (),
+}
+
+impl Transaction {
+    /// Gets a variable, including writes staged by the transaction, see [var::get]
+    pub fn get<T: FromBytesOwned>(&self, key: impl AsRef<str>) -> Result<Option<T>, Error> {
+        var::get(key)
+    }
+
+    /// Stages a write, see [var::set]
+    pub fn set(&self, key: impl AsRef<str>, val: impl ToMemory) -> Result<(), Error> {
+        var::set(key, val)
+    }
+
+    /// Stages the removal of a variable, see [var::remove]
+    pub fn remove(&self, key: impl AsRef<str>) -> Result<(), Error> {
+        var::remove(key)
+    }
+}
+
+/// Runs `f` in a transaction. Variable writes made while `f` runs, including writes through
+/// [var::set], [var::Namespace] and the var collections, are staged and only applied when
+/// `f` returns `Ok`. When `f` returns an error every staged write is discarded.
+///
+/// Committing reads the previous value of every staged variable before writing it. If the
+/// host rejects a write, i.e. with [var::VarError::QuotaExceeded], the writes that were
+/// already applied are reverted to their previous values and the error is returned, so the
+/// host never keeps part of a transaction. When the variable cache is enabled the writes
+/// are moved to the cache instead, and errors are returned by [var::flush], which reverts
+/// the writes it already applied in the same way.
+///
+/// `#[plugin_fn(transactional_vars)]` runs the whole function in a transaction, which is
+/// committed only when the function returns `Ok`.
+///
+/// # Examples
+///
+/// ```
+/// var::transaction(|tx| {
+///     let balance: i64 = tx.get("balance")?.unwrap_or(0);
+///     tx.set("balance", balance - amount)?;
+///     if balance < amount {
+///         return Err(Error::msg("Insufficient funds"));
+///     }
+///     tx.set("last_withdrawal", amount)
+/// })?;
+/// ```
+pub fn transaction<R>(f: impl FnOnce(&Transaction) -> Result<R, Error>) -> Result<R, Error> {
+    begin_transaction();
+    match f(&Transaction { _private: () }) {
+        Ok(x) => {
+            commit_transaction()?;
+            Ok(x)
+        }
+        Err(e) => {
+            rollback_transaction();
+            Err(e)
+        }
+    }
+}
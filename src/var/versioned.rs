@@ -0,0 +1,153 @@
+use crate::*;
+
+type Migration = Box<dyn Fn(&[u8]) -> Result<Vec<u8>, Error>>;
+
+/// A variable that stores a schema version alongside its value, and migrates values written
+/// with older versions when they're read
+///
+/// The value is stored in `key` unchanged, and the version is stored in `<key>#version` as a
+/// 4 byte little-endian `u32`, both are written in a single [var::transaction]. Values
+/// without a version variable, such as those written using [var::set], are treated as
+/// version 0.
+///
+/// Each migration converts a value from one version to the next. When an older value is
+/// read, the migrations are applied in order and the upgraded value is written back.
+///
+/// # Examples
+///
+/// ```
+/// let settings = var::VersionedVar::<Json<SettingsV2>>::new("settings", 2)
+///     .migration(0, |Json(v): Json<SettingsV0>| Ok(Json(SettingsV1::from(v))))
+///     .migration(1, |Json(v): Json<SettingsV1>| Ok(Json(SettingsV2::from(v))));
+/// let Json(current) = settings.get()?.unwrap_or_default();
+/// ```
+pub struct VersionedVar<T> {
+    key: String,
+    version: u32,
+    migrations: Vec<(u32, Migration)>,
+    _t: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T> std::fmt::Debug for VersionedVar<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VersionedVar")
+            .field("key", &self.key)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+impl<T> VersionedVar<T> {
+    /// Create a new `VersionedVar`, `version` is the current version of `T`
+    pub fn new(key: impl Into<String>, version: u32) -> Self {
+        VersionedVar {
+            key: key.into(),
+            version,
+            migrations: vec![],
+            _t: std::marker::PhantomData,
+        }
+    }
+
+    /// The name of the variable
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The current version
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Register a migration from version `from` to version `from + 1`
+    pub fn migration<A: FromBytesOwned, B: for<'a> ToBytes<'a>>(
+        mut self,
+        from: u32,
+        f: impl Fn(A) -> Result<B, Error> + 'static,
+    ) -> Self {
+        self.migrations.push((
+            from,
+            Box::new(move |data| {
+                let b = f(A::from_bytes_owned(data)?)?;
+                Ok(b.to_bytes()?.as_ref().to_vec())
+            }),
+        ));
+        self
+    }
+
+    /// Removes the variable and its version
+    pub fn remove(&self) -> Result<(), Error> {
+        var::transaction(|tx| {
+            tx.remove(&self.key)?;
+            tx.remove(self.version_key())
+        })
+    }
+
+    fn version_key(&self) -> String {
+        format!("{}#version", self.key)
+    }
+
+    fn stored_version(&self) -> Result<u32, Error> {
+        let Some(version) = var::get_bytes(&self.version_key())? else {
+            return Ok(0);
+        };
+        match <[u8; 4]>::try_from(version.as_slice()) {
+            Ok(version) => Ok(u32::from_le_bytes(version)),
+            Err(_) => anyhow::bail!("Variable {:?} has an invalid version", self.key),
+        }
+    }
+
+    fn write(&self, data: &[u8]) -> Result<(), Error> {
+        var::transaction(|tx| {
+            tx.set(&self.key, data)?;
+            tx.set(self.version_key(), self.version.to_le_bytes().as_slice())
+        })
+    }
+}
+
+impl<T: FromBytesOwned> VersionedVar<T> {
+    /// Gets the value, migrating it to the current version if needed
+    pub fn get(&self) -> Result<Option<T>, Error> {
+        let Some(mut data) = var::get_bytes(&self.key)? else {
+            return Ok(None);
+        };
+        let mut version = self.stored_version()?;
+
+        if version > self.version {
+            anyhow::bail!(
+                "Variable {:?} has version {version}, which is newer than the current version {}",
+                self.key,
+                self.version
+            );
+        }
+        if version == self.version {
+            return Ok(Some(T::from_bytes_owned(&data)?));
+        }
+
+        while version < self.version {
+            let Some((_, migrate)) = self.migrations.iter().find(|(from, _)| *from == version)
+            else {
+                anyhow::bail!(
+                    "Unable to migrate variable {:?}, no migration from version {version}",
+                    self.key
+                );
+            };
+            data = migrate(&data).map_err(|e| {
+                e.context(format!(
+                    "Unable to migrate variable {:?} from version {version}",
+                    self.key
+                ))
+            })?;
+            version += 1;
+        }
+
+        self.write(&data)?;
+        Ok(Some(T::from_bytes_owned(&data)?))
+    }
+}
+
+impl<T: for<'a> ToBytes<'a>> VersionedVar<T> {
+    /// Sets the value, tagged with the current version
+    pub fn set(&self, val: &T) -> Result<(), Error> {
+        self.write(val.to_bytes()?.as_ref())
+    }
+}